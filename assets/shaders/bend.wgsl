@@ -0,0 +1,38 @@
+#define_import_path mia::bend
+
+struct Bender {
+    position: vec3<f32>,
+    radius: f32,
+    strength: f32,
+};
+
+// 附近的 Bender 把植物从 root 推开; h 为高度比例, 0 根部不动, 1 叶尖推开最多
+// benders 的长度与 bend::MAX_BENDERS 一致
+fn bend_offset(benders: array<Bender, 16>, root: vec3<f32>, h: f32) -> vec3<f32> {
+    var offset = vec2<f32>(0.0);
+    var max_strength = 0.0;
+    // 按值传入的数组只能用常量下标, 复制到局部变量后才能在循环中索引
+    var list = benders;
+    for (var i = 0u; i < 16u; i += 1u) {
+        let bender = list[i];
+        if bender.radius <= 0.0 || bender.strength <= 0.0 {
+            continue;
+        }
+        let distance = length(root - bender.position);
+        let falloff = clamp(1.0 - distance / bender.radius, 0.0, 1.0);
+        // 正好在 Bender 下方时没有方向, 顺着 x 轴倒下
+        var away = root.xz - bender.position.xz;
+        away = select(vec2<f32>(1.0, 0.0), normalize(away), length(away) > 0.0001);
+        offset += away * falloff * falloff * bender.strength;
+        max_strength = max(max_strength, bender.strength);
+    }
+    // 多个 Bender 叠加时不超过最强的一个
+    let amount = length(offset);
+    if amount > max_strength {
+        offset *= max_strength / amount;
+    }
+    // 被推开的同时压低, 保持草叶长度大致不变
+    let bent = offset * h * h;
+    let press = length(bent) * 0.5;
+    return vec3<f32>(bent.x, -press, bent.y);
+}
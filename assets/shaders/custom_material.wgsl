@@ -0,0 +1,35 @@
+#define_import_path mia::custom_material
+
+#import mia::wind Wind, wind_offset
+#import mia::bend Bender, bend_offset
+
+// 与 Rust 的 `CustomMaterial` 一致, 主渲染和阴影预渲染共用
+struct CustomMaterial {
+    color: vec4<f32>,
+    tip_color: vec4<f32>,
+    height_range: vec2<f32>,
+    alpha_cutoff: f32,
+    roughness: f32,
+    translucency: f32,
+    wind: Wind,
+};
+@group(1) @binding(0)
+var<uniform> material: CustomMaterial;
+@group(1) @binding(1)
+var base_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var base_sampler: sampler;
+// 所有材质共用, 由 `bend` 写入
+@group(1) @binding(3)
+var<storage, read> benders: array<Bender, 16>;
+
+// 局部 y 映射到高度比例 [0, 1]
+fn height_ratio(local_y: f32) -> f32 {
+    let range = material.height_range;
+    return clamp((local_y - range.x) / max(range.y - range.x, 0.0001), 0.0, 1.0);
+}
+
+// 风和压草叠加后的偏移; origin 为模型原点的世界坐标, 整株一起摆
+fn sway_offset(origin: vec3<f32>, h: f32, time: f32) -> vec3<f32> {
+    return wind_offset(material.wind, origin.xz, h, 0.0, time) + bend_offset(benders, origin, h);
+}
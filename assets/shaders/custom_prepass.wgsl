@@ -0,0 +1,65 @@
+// `CustomMaterial` 的阴影预渲染: 与主渲染同样摇摆, 阴影跟着植物一起动
+#import bevy_pbr::prepass_bindings view, globals
+#import bevy_pbr::mesh_bindings   mesh
+#import mia::custom_material material, base_texture, base_sampler, height_ratio, sway_offset
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+#ifdef VERTEX_UVS
+    @location(1) uv: vec2<f32>,
+#endif
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+#ifdef VERTEX_UVS
+    @location(0) uv: vec2<f32>,
+#endif
+#ifdef DEPTH_CLAMP_ORTHO
+    @location(1) clip_position_unclamped: vec4<f32>,
+#endif
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+
+    let h = height_ratio(vertex.position.y);
+    let origin = mesh.model * vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    var world_position = mesh.model * vec4<f32>(vertex.position, 1.0);
+    world_position += vec4<f32>(sway_offset(origin.xyz, h, globals.time), 0.0);
+    out.clip_position = view.view_proj * world_position;
+
+#ifdef DEPTH_CLAMP_ORTHO
+    // 平行光阴影: 光源后面的物体压到近平面上, 深度由片元着色器写回
+    out.clip_position_unclamped = out.clip_position;
+    out.clip_position.z = min(out.clip_position.z, 1.0);
+#endif
+#ifdef VERTEX_UVS
+    out.uv = vertex.uv;
+#endif
+    return out;
+}
+
+// 贴图镂空的部分不投影
+fn cutout(input: VertexOutput) {
+#ifdef VERTEX_UVS
+    let alpha = material.color.a * textureSample(base_texture, base_sampler, input.uv).a;
+    if alpha < material.alpha_cutoff {
+        discard;
+    }
+#endif
+}
+
+#ifdef DEPTH_CLAMP_ORTHO
+@fragment
+fn fragment(input: VertexOutput) -> @builtin(frag_depth) f32 {
+    cutout(input);
+    return input.clip_position_unclamped.z;
+}
+#else
+@fragment
+fn fragment(input: VertexOutput) {
+    cutout(input);
+}
+#endif
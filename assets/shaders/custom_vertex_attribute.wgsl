@@ -1,65 +1,77 @@
 #import bevy_pbr::mesh_bindings   mesh
-#import bevy_pbr::mesh_functions  mesh_position_local_to_clip
 #import bevy_pbr::mesh_view_bindings globals
-
-struct CustomMaterial {
-    color: vec4<f32>,
-};
-@group(1) @binding(0)
-var<uniform> material: CustomMaterial;
-
-fn grassSwing(input: f32) -> f32 {
-    // 将输入映射到[0, 1]范围，假设输入的范围是[-1, 1]
-    let scaledInput: f32 = (input + 1.0) / 2.0;
-
-    // 计算振幅，使用指数增长
-    let amplitude: f32 = 2.0 * pow(2.0, scaledInput);
-
-    // 使用正弦函数来模拟摇摆，可以根据需要调整频率
-    let frequency: f32 = 2.0 * globals.time; // 调整频率
-    let swingValue: f32 = amplitude * sin(frequency);
-
-    // 返回摇摆值
-    return swingValue;
-}
+#import bevy_pbr::mesh_functions  mesh_position_local_to_world, mesh_position_world_to_clip, mesh_normal_local_to_world
+#import mia::custom_material material, base_texture, base_sampler, height_ratio, sway_offset
+#import mia::foliage foliage_lighting
 
 struct Vertex {
     @location(0) position: vec3<f32>,
-//    @location(1) color: vec4<f32>,
-//    @location(1) blend_color: vec4<f32>,
+#ifdef VERTEX_UVS
+    @location(1) uv: vec2<f32>,
+#endif
+#ifdef VERTEX_COLORS
+    @location(2) color: vec4<f32>,
+#endif
+#ifdef VERTEX_NORMALS
+    @location(3) normal: vec3<f32>,
+#endif
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
-    @location(0) blend_color: vec4<f32>,
+    @location(0) world_position: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) blend_color: vec4<f32>,
+#ifdef VERTEX_UVS
+    @location(3) uv: vec2<f32>,
+#endif
 };
 
 @vertex
 fn vertex(vertex: Vertex) -> VertexOutput {
     var out: VertexOutput;
 
-    // 计算摇摆偏移 dx = A * f(h) * sin(B * t) 这里的 A 是晃动最大幅度, B 是晃动速度, f(h) 则代表高度对幅度的影响函数
-    let sway_offset = grassSwing(vertex.position.y) ;
-    // 将草的位置进行摇摆
-    var new_position = vec4<f32>(vertex.position, 1.0);
-    new_position.x += sway_offset;
+    let h = height_ratio(vertex.position.y);
+    let origin = mesh_position_local_to_world(mesh.model, vec4<f32>(0.0, 0.0, 0.0, 1.0));
+    var world_position = mesh_position_local_to_world(mesh.model, vec4<f32>(vertex.position, 1.0));
+    world_position += vec4<f32>(sway_offset(origin.xyz, h, globals.time), 0.0);
+    out.world_position = world_position;
+    out.clip_position = mesh_position_world_to_clip(world_position);
+
+#ifdef VERTEX_NORMALS
+    out.world_normal = mesh_normal_local_to_world(vertex.normal);
+#else
+    // 没有法线的网格按朝上处理
+    out.world_normal = vec3<f32>(0.0, 1.0, 0.0);
+#endif
 
-//    out.blend_color = vertex.color;
-    out.clip_position = mesh_position_local_to_clip(
-            mesh.model,
-            new_position
-        );
-    out.blend_color = vec4<f32>(1.0, 1.0, 1.0, 1.0);
+    // 从底部颜色渐变到顶部颜色
+    out.blend_color = mix(material.color, material.tip_color, h);
+#ifdef VERTEX_COLORS
+    out.blend_color *= vertex.color;
+#endif
+#ifdef VERTEX_UVS
+    out.uv = vertex.uv;
+#endif
     return out;
 }
 
-struct FragmentInput {
-    @builtin(position) clip_position: vec4<f32>,
-    @location(0) blend_color: vec4<f32>,
-};
-
 @fragment
-fn fragment(input: FragmentInput) -> @location(0) vec4<f32> {
-//    return material.color * input.blend_color;
-    return input.blend_color;
+fn fragment(@builtin(front_facing) is_front: bool, input: VertexOutput) -> @location(0) vec4<f32> {
+    var color = input.blend_color;
+#ifdef VERTEX_UVS
+    color *= textureSample(base_texture, base_sampler, input.uv);
+#endif
+    if color.a < material.alpha_cutoff {
+        discard;
+    }
+    return foliage_lighting(
+        color,
+        material.roughness,
+        material.translucency,
+        input.world_position,
+        input.world_normal,
+        is_front,
+        input.clip_position,
+    );
 }
@@ -0,0 +1,48 @@
+#define_import_path mia::foliage
+
+#import bevy_pbr::mesh_view_bindings view, lights
+#import bevy_pbr::mesh_bindings mesh
+#import bevy_pbr::pbr_functions as pbr_functions
+#import bevy_core_pipeline::tonemapping tone_mapping
+
+// 叶片的 PBR 光照(接收阴影), 再加上逆光时透过叶片的光; translucency 为透光比例
+fn foliage_lighting(
+    base_color: vec4<f32>,
+    roughness: f32,
+    translucency: f32,
+    world_position: vec4<f32>,
+    world_normal: vec3<f32>,
+    is_front: bool,
+    frag_coord: vec4<f32>,
+) -> vec4<f32> {
+    var pbr_input = pbr_functions::pbr_input_new();
+    pbr_input.material.base_color = base_color;
+    pbr_input.material.perceptual_roughness = roughness;
+    pbr_input.material.metallic = 0.0;
+    pbr_input.frag_coord = frag_coord;
+    pbr_input.world_position = world_position;
+    // 叶片两面都可见, 背面的法线翻转朝向相机
+    pbr_input.world_normal = pbr_functions::prepare_world_normal(world_normal, true, is_front);
+    pbr_input.is_orthographic = view.projection[3].w == 1.0;
+    pbr_input.N = normalize(pbr_input.world_normal);
+    pbr_input.V = pbr_functions::calculate_view(world_position, pbr_input.is_orthographic);
+    pbr_input.flags = mesh.flags;
+    var color = pbr_functions::pbr(pbr_input);
+
+    // 光从叶片背面照过来时透光, 视线正对光源时最亮
+    var back = vec3<f32>(0.0);
+    for (var i = 0u; i < lights.n_directional_lights; i += 1u) {
+        let light = lights.directional_lights[i];
+        let L = light.direction_to_light;
+        let through = saturate(-dot(pbr_input.N, L));
+        let facing = pow(saturate(dot(-pbr_input.V, L)), 4.0);
+        back += light.color.rgb * (0.5 * through + 0.5 * facing);
+    }
+    // 与 Lambert 漫反射一样除以 π
+    color = vec4<f32>(color.rgb + back * base_color.rgb * translucency * 0.31830988, color.a);
+
+#ifdef TONEMAP_IN_SHADER
+    color = tone_mapping(color, view.color_grading);
+#endif
+    return color;
+}
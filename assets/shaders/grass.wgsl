@@ -0,0 +1,51 @@
+#import bevy_pbr::mesh_bindings   mesh
+#import bevy_pbr::mesh_view_bindings globals
+#import bevy_pbr::mesh_functions  mesh_position_local_to_world, mesh_position_world_to_clip, mesh_normal_local_to_world
+#import mia::custom_material material
+#import mia::grass blade_position, rotate_blade
+#import mia::foliage foliage_lighting
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    // 草叶根部的世界坐标和高度
+    @location(3) i_position_height: vec4<f32>,
+    // 朝向 (cos, sin), 明暗, 摇摆相位
+    @location(4) i_rotation_shade_phase: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) color: vec4<f32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+
+    let position = blade_position(vertex.position, vertex.i_position_height, vertex.i_rotation_shade_phase, globals.time);
+    out.world_position = mesh_position_local_to_world(mesh.model, vec4<f32>(position, 1.0));
+    out.clip_position = mesh_position_world_to_clip(out.world_position);
+    out.world_normal = mesh_normal_local_to_world(rotate_blade(vertex.normal, vertex.i_rotation_shade_phase));
+
+    // 从根部颜色渐变到叶尖颜色, 每根草叶明暗略有不同
+    let color = mix(material.color, material.tip_color, vertex.position.y);
+    out.color = vec4<f32>(color.rgb * vertex.i_rotation_shade_phase.z, color.a);
+    return out;
+}
+
+@fragment
+fn fragment(@builtin(front_facing) is_front: bool, input: VertexOutput) -> @location(0) vec4<f32> {
+    return foliage_lighting(
+        input.color,
+        material.roughness,
+        material.translucency,
+        input.world_position,
+        input.world_normal,
+        is_front,
+        input.clip_position,
+    );
+}
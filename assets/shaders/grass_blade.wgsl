@@ -0,0 +1,27 @@
+#define_import_path mia::grass
+
+#import mia::custom_material material, benders
+#import mia::wind wind_offset
+#import mia::bend bend_offset
+
+// 草叶网格局部坐标 -> 草地网格局部坐标: 按实例的高度缩放、旋转, 放到根部, 再随风摇摆、被推开.
+// position_height: 根部坐标和高度; rotation_shade_phase: 朝向 (cos, sin), 明暗, 摇摆相位
+fn blade_position(position: vec3<f32>, position_height: vec4<f32>, rotation_shade_phase: vec4<f32>, time: f32) -> vec3<f32> {
+    let height = position_height.w;
+    var local = position * vec3<f32>(1.0, height, 1.0);
+    local = rotate_blade(local, rotation_shade_phase);
+
+    // 随风摇摆, 越靠近叶尖摆动越大; 相位扰动让相邻草叶不完全同步
+    let root = position_height.xyz;
+    let phase = rotation_shade_phase.w * 0.3;
+    var offset = wind_offset(material.wind, root.xz, position.y, phase, time);
+    // 被附近的角色推开; Bender 的强度按默认草高 0.4 米设定, 矮草推开得少一些
+    offset += bend_offset(benders, root, position.y) * min(height / 0.4, 1.0);
+    return local + root + offset;
+}
+
+fn rotate_blade(v: vec3<f32>, rotation_shade_phase: vec4<f32>) -> vec3<f32> {
+    let c = rotation_shade_phase.x;
+    let s = rotation_shade_phase.y;
+    return vec3<f32>(v.x * c - v.z * s, v.y, v.x * s + v.z * c);
+}
@@ -0,0 +1,37 @@
+// 草叶的阴影预渲染, 与 grass.wgsl 同样摇摆
+#import bevy_pbr::prepass_bindings view, globals
+#import bevy_pbr::mesh_bindings   mesh
+#import mia::grass blade_position
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(3) i_position_height: vec4<f32>,
+    @location(4) i_rotation_shade_phase: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+#ifdef DEPTH_CLAMP_ORTHO
+    @location(0) clip_position_unclamped: vec4<f32>,
+#endif
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+
+    let position = blade_position(vertex.position, vertex.i_position_height, vertex.i_rotation_shade_phase, globals.time);
+    out.clip_position = view.view_proj * mesh.model * vec4<f32>(position, 1.0);
+#ifdef DEPTH_CLAMP_ORTHO
+    out.clip_position_unclamped = out.clip_position;
+    out.clip_position.z = min(out.clip_position.z, 1.0);
+#endif
+    return out;
+}
+
+#ifdef DEPTH_CLAMP_ORTHO
+@fragment
+fn fragment(input: VertexOutput) -> @builtin(frag_depth) f32 {
+    return input.clip_position_unclamped.z;
+}
+#endif
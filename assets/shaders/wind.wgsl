@@ -0,0 +1,38 @@
+#define_import_path mia::wind
+
+struct Wind {
+    direction: vec2<f32>,
+    strength: f32,
+    speed: f32,
+    wavelength: f32,
+    gust_strength: f32,
+    gust_scale: f32,
+};
+
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(127.1, 311.7))) * 43758.5453);
+}
+
+// 平滑插值的值噪声, 范围 [0, 1]
+fn value_noise(p: vec2<f32>) -> f32 {
+    let i = floor(p);
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+    let a = hash(i);
+    let b = hash(i + vec2<f32>(1.0, 0.0));
+    let c = hash(i + vec2<f32>(0.0, 1.0));
+    let d = hash(i + vec2<f32>(1.0, 1.0));
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
+}
+
+// 世界坐标 xz 处的风偏移; h 为高度比例, 0 根部不动, 1 叶尖摆动最大; phase 为每株植物的相位扰动.
+// 时间由调用者传入, 主渲染和阴影预渲染的 globals 绑定位置不同
+fn wind_offset(wind: Wind, world_xz: vec2<f32>, h: f32, phase: f32, t: f32) -> vec3<f32> {
+    // 风浪沿风向推进, 同一道风浪经过的位置相位相同
+    let distance = dot(world_xz, wind.direction) - t * wind.speed;
+    let wave = 0.5 + 0.5 * sin(distance / wind.wavelength * 6.2831853 + phase);
+    // 阵风: 随风移动的低频噪声
+    let gust = value_noise((world_xz - wind.direction * t * wind.speed) / wind.gust_scale);
+    let amount = (wind.strength * wave + wind.gust_strength * gust) * h * h;
+    return vec3<f32>(wind.direction.x, 0.0, wind.direction.y) * amount;
+}
@@ -1,27 +1,40 @@
+use std::collections::HashMap;
+use bevy::core_pipeline::clear_color::ClearColorConfig;
 use bevy::math::vec2;
 use bevy::prelude::*;
-use bevy::render::view::RenderLayers;
 use bevy_rapier2d::prelude::*;
 
-use crate::{Direction, AnimationIndices, AnimationTimer, CharacterState, GameEvent, GameState, UID, Character, CharacterName, ActionStage, Hitbox, Hurtbox, Blockbox, Rectbox, OwnerUID, Action, OwnerCharacter, CMD};
+use crate::{FlatCamera, Direction, AnimationIndices, AnimationTimer, CharacterState, GameEvent, GameState, UID, CharacterName, ActionStage, Hitbox, Hurtbox, Blockbox, Rectbox, OwnerUID, Action, Palette, CMD, CombatEvent, Health, Meter};
 use crate::loading::{Characters, CharactersTextureAtlas};
+use crate::audio::settings_closed;
+use crate::select::{PlayerSelection, Selections};
+use crate::stage::CurrentStage;
+use crate::meter::SuperFreeze;
+use crate::vfx::Hitstop;
+#[cfg(debug_assertions)]
+use crate::plugins::DebugOverlayPlugin;
 
 pub struct ActionPlugin;
 
 impl Plugin for ActionPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(OwnerUID(1))
-            .insert_resource(OwnerCharacter("skeleton".to_string()))
+            .add_event::<GameEvent>()
+            .add_event::<CombatEvent>()
+            .register_type::<Health>()
+            .register_type::<Meter>()
             .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
-            .add_plugins(RapierDebugRenderPlugin::default())
             .add_systems(OnEnter(GameState::Playing), setup)
-            .add_systems(Update, input.run_if(in_state(GameState::Playing)))
+            .add_systems(Update, input.run_if(in_state(GameState::Playing)).run_if(settings_closed))
             .add_systems(Update, state.run_if(in_state(GameState::Playing)))
             // .add_systems(Update, movement.run_if(in_state(GameState::Playing)))
             .add_systems(Update, action.run_if(in_state(GameState::Playing)))
             .add_systems(Update, animation.run_if(in_state(GameState::Playing)))
-            .add_systems(Last, damage.run_if(in_state(GameState::Playing)))
-            .add_systems(Last, hurtbox.run_if(in_state(GameState::Playing)));
+            .add_systems(Update, anchor.after(state).after(animation).run_if(in_state(GameState::Playing)))
+            .add_systems(Last, damage.run_if(in_state(GameState::Playing)));
+
+        #[cfg(debug_assertions)]
+        app.add_plugins(DebugOverlayPlugin);
     }
 }
 
@@ -29,38 +42,43 @@ fn setup(
     mut commands: Commands,
     mut characters: Res<Characters>,
     mut characters_texture_atlas: Res<CharactersTextureAtlas>,
+    selections: Res<Selections>,
+    stage: Res<CurrentStage>,
 ) {
-    commands
-        .spawn(Collider::cuboid(10., 1000.))
-        .insert(TransformBundle::from(Transform::from_xyz(-960. / 2., -100., 0.0)));
-
-    commands
-        .spawn(Collider::cuboid(10., 1000.))
-        .insert(TransformBundle::from(Transform::from_xyz(960. / 2., -100., 0.0)));
-    commands
-        .spawn((
-            Collider::cuboid(1000.0, 20.0),
-            TransformBundle::from(Transform::from_xyz(0.0, -250.0, 0.0)),
-            Restitution {
-                coefficient: 0.,
-                combine_rule: CoefficientCombineRule::Min,
-            },
-            Friction {
-                coefficient: 2.,
-                combine_rule: CoefficientCombineRule::Max,
-            },
-        ));
-
-    let character_name = "skeleton";
-    let character = characters.get(character_name).unwrap();
-    let action_name = "idle";
-    let action = character.actions.get(action_name).unwrap();
-    let texture_atlas = characters_texture_atlas.get(character_name).unwrap().get(action_name).unwrap().clone();
-    create_character(&mut commands, texture_atlas.clone(), character_name, action, 1);
-    create_character(&mut commands, texture_atlas.clone(), character_name, action, 2);
+    // 叠加在 3D 场景之上的 2D 相机
+    commands.spawn((Camera2dBundle {
+        camera: Camera {
+            order: 1,
+            ..default()
+        },
+        camera_2d: Camera2d {
+            clear_color: ClearColorConfig::None,
+        },
+        ..default()
+    }, FlatCamera));
+
+    // 按选人界面的选择生成角色, 没有选择时使用第一个角色, 同角色时自动错开调色板
+    let default_character = characters.keys().min().cloned().unwrap();
+    let mut selections = Selections(selections.clone());
+    for uid in [1, 2] {
+        selections.entry(UID(uid)).or_insert_with(|| PlayerSelection {
+            character: default_character.clone(),
+            palette: 0,
+        });
+    }
+    selections.resolve_mirror_palettes(&characters);
+    for uid in [1, 2] {
+        let selection = &selections[&UID(uid)];
+        let character_name = selection.character.as_str();
+        let character = characters.get(character_name).unwrap();
+        let action_name = "idle";
+        let action = character.actions.get(action_name).unwrap();
+        let texture_atlas = characters_texture_atlas.get_action(character_name, selection.palette, action_name).unwrap().clone();
+        create_character(&mut commands, texture_atlas, character_name, action, uid, selection.palette, stage.spawn_point(UID(uid)));
+    }
 }
 
-fn create_character(commands: &mut Commands, texture_atlas: Handle<TextureAtlas>, character_name: &str, action: &Action, uid: u32) {
+fn create_character(commands: &mut Commands, texture_atlas: Handle<TextureAtlas>, character_name: &str, action: &Action, uid: u32, palette: usize, position: Vec3) {
     commands.spawn((
         RigidBody::Dynamic,
         Collider::capsule_y(15., 15.),
@@ -79,12 +97,12 @@ fn create_character(commands: &mut Commands, texture_atlas: Handle<TextureAtlas>
         SpriteSheetBundle {
             texture_atlas,
             sprite: TextureAtlasSprite::new(0),
-            transform: Transform::from_xyz(0., 0., 1.),
+            transform: Transform::from_translation(position),
             ..default()
         },
         AnimationIndices { first: 0, last: action.frames.len() - 1, repeat: true },
         AnimationTimer(Timer::from_seconds(action.duration / action.frames.len() as f32, TimerMode::Repeating)),
-        CharacterName(character_name.to_string()),
+        (CharacterName(character_name.to_string()), Palette(palette), Health::default(), Meter::default()),
         CharacterState::Idle,
         Direction::Left,
         Velocity {
@@ -99,8 +117,8 @@ fn input(
     input: Res<Input<KeyCode>>,
     mut ew: EventWriter<GameEvent>,
     mut owner: ResMut<OwnerUID>,
-    owner_character: Res<OwnerCharacter>,
     characters: Res<Characters>,
+    fighters: Query<(&UID, &CharacterName)>,
 ) {
     let mut events = Vec::new();
 
@@ -115,17 +133,26 @@ fn input(
     } else if input.pressed(KeyCode::D) {
         events.push(GameEvent::Right(UID(owner.0)));
     }
-    if input.pressed(KeyCode::J) {
-        let character = characters.get(owner_character.0.as_str()).unwrap();
-        events.push(GameEvent::Action(UID(owner.0), character.commands.get(&CMD::J).unwrap().clone()));
-    }
-    if input.pressed(KeyCode::K) {
-        let character = characters.get(owner_character.0.as_str()).unwrap();
-        events.push(GameEvent::Action(UID(owner.0), character.commands.get(&CMD::K).unwrap().clone()));
-    }
-    if input.pressed(KeyCode::I) {
-        let character = characters.get(owner_character.0.as_str()).unwrap();
-        events.push(GameEvent::Action(UID(owner.0), character.commands.get(&CMD::I).unwrap().clone()));
+    // 指令映射取自当前控制角色自己的角色数据, 按住 L 时发动动作的 EX 版本
+    let character = fighters
+        .iter()
+        .find(|(uid, _)| uid.0 == owner.0)
+        .and_then(|(_, character_name)| characters.get(character_name.as_str()));
+    if let Some(character) = character {
+        for (key, cmd) in [(KeyCode::J, CMD::J), (KeyCode::K, CMD::K), (KeyCode::I, CMD::I), (KeyCode::U, CMD::U)] {
+            if !input.pressed(key) {
+                continue;
+            }
+            let Some(action_name) = character.commands.get(&cmd) else {
+                continue;
+            };
+            let ex_action = character.actions.get(action_name).and_then(|action| action.ex_action.as_ref());
+            let action_name = match ex_action {
+                Some(ex_action) if input.pressed(KeyCode::L) => ex_action,
+                _ => action_name,
+            };
+            events.push(GameEvent::Action(UID(owner.0), action_name.clone()));
+        }
     }
     // if input.pressed(KeyCode::L) {
     //     events.push(GameEvent::Dodge(1));
@@ -136,15 +163,22 @@ fn input(
     ew.send_batch(events);
 }
 
+#[allow(clippy::type_complexity)]
 fn state(
     mut commands: Commands,
     mut events: EventReader<GameEvent>,
+    mut combat_events: EventWriter<CombatEvent>,
+    mut freeze: ResMut<SuperFreeze>,
     mut characters: Res<Characters>,
     mut characters_texture_atlas: Res<CharactersTextureAtlas>,
-    mut query: Query<(Entity, &UID, &mut Velocity, &mut CharacterState, &CharacterName, &mut Direction, &mut AnimationIndices, &mut AnimationTimer, &mut TextureAtlasSprite, &mut Handle<TextureAtlas>)>,
+    mut query: Query<(Entity, &UID, &mut Velocity, &mut CharacterState, &CharacterName, &Palette, &mut Direction, &mut AnimationIndices, &mut AnimationTimer, &mut TextureAtlasSprite, &mut Handle<TextureAtlas>, &mut Health, &mut Meter)>,
 ) {
     for event in events.iter() {
-        for (entity, hituid, mut velocity, mut state, character_name, mut direction, mut indices, mut timer, mut sprite, mut texture) in &mut query {
+        for (entity, hituid, mut velocity, mut state, character_name, palette, mut direction, indices, timer, mut sprite, mut texture, mut health, mut meter) in &mut query {
+            // 超必杀定格期间被冻结的角色只会受击
+            if freeze.is_frozen(*hituid) && !matches!(event, GameEvent::Hit { .. }) {
+                continue;
+            }
             match (event, state.clone()) {
                 (GameEvent::Idle(uid), CharacterState::Walk) => {
                     if uid != hituid {
@@ -154,9 +188,9 @@ fn state(
                     velocity.linvel = Vec2::new(0.0, 0.0);
                     let action_name = state.to_string();
                     let action = characters.get(character_name.as_str()).unwrap().actions.get(&action_name).unwrap();
-                    let texture_atlas = characters_texture_atlas.get(character_name.as_str()).unwrap().get(&action_name).unwrap().clone();
+                    let texture_atlas = characters_texture_atlas.get_action(character_name, palette.0, &action_name).unwrap().clone();
                     *texture = texture_atlas;
-                    info!("uid: {:?}", uid);
+                    debug!("uid: {:?}", uid);
                     set_character_action(sprite, indices, timer, action);
                 }
                 (GameEvent::Left(uid) | GameEvent::Right(uid), CharacterState::Idle) => {
@@ -180,9 +214,9 @@ fn state(
 
                     let action_name = state.to_string();
                     let action = characters.get(character_name.as_str()).unwrap().actions.get(&action_name).unwrap();
-                    let texture_atlas = characters_texture_atlas.get(character_name.as_str()).unwrap().get(&action_name).unwrap().clone();
+                    let texture_atlas = characters_texture_atlas.get_action(character_name, palette.0, &action_name).unwrap().clone();
                     *texture = texture_atlas;
-                    info!("uid: {:?}", uid);
+                    debug!("uid: {:?}", uid);
                     set_character_action(sprite, indices, timer, action);
                 }
                 (GameEvent::Left(uid) | GameEvent::Right(uid), CharacterState::Walk) => {
@@ -207,13 +241,16 @@ fn state(
                     if uid != hituid {
                         continue;
                     }
+                    let action = characters.get(character_name.as_str()).unwrap().actions.get(action_name).unwrap();
+                    if !spend_meter(&mut meter, &mut freeze, *uid, action) {
+                        continue;
+                    }
                     *state = CharacterState::Action(action_name.clone());
                     velocity.linvel = Vec2::new(0.0, 0.0);
 
-                    let action = characters.get(character_name.as_str()).unwrap().actions.get(action_name).unwrap();
-                    let texture_atlas = characters_texture_atlas.get(character_name.as_str()).unwrap().get(action_name.as_str()).unwrap().clone();
+                    let texture_atlas = characters_texture_atlas.get_action(character_name, palette.0, action_name.as_str()).unwrap().clone();
                     *texture = texture_atlas;
-                    info!("uid: {:?}", uid);
+                    debug!("uid: {:?}", uid);
                     set_character_action(sprite, indices, timer, action);
                     if let Some(impulse) = action.internal_impulse {
                         let impulse = match *direction {
@@ -246,11 +283,14 @@ fn state(
                         new_action_name.to_string()
                     };
 
-                    *state = CharacterState::Action(action_name.clone());
                     let action = characters.get(character_name.as_str()).unwrap().actions.get(action_name.as_str()).unwrap();
-                    let texture_atlas = characters_texture_atlas.get(character_name.as_str()).unwrap().get(&action_name).unwrap().clone();
+                    if !spend_meter(&mut meter, &mut freeze, *uid, action) {
+                        continue;
+                    }
+                    *state = CharacterState::Action(action_name.clone());
+                    let texture_atlas = characters_texture_atlas.get_action(character_name, palette.0, &action_name).unwrap().clone();
                     *texture = texture_atlas;
-                    info!("uid: {:?}", uid);
+                    debug!("uid: {:?}", uid);
                     set_character_action(sprite, indices, timer, action);
                     if let Some(impulse) = action.internal_impulse {
                         let impulse = match *direction {
@@ -270,35 +310,50 @@ fn state(
                     *state = CharacterState::Idle;
                     let action_name = state.to_string();
                     let action = characters.get(character_name.as_str()).unwrap().actions.get(&action_name).unwrap();
-                    let texture_atlas = characters_texture_atlas.get(character_name.as_str()).unwrap().get(&action_name).unwrap().clone();
+                    let texture_atlas = characters_texture_atlas.get_action(character_name, palette.0, &action_name).unwrap().clone();
                     *texture = texture_atlas;
-                    info!("uid: {:?}", uid);
+                    debug!("uid: {:?}", uid);
                     set_character_action(sprite, indices, timer, action);
                     // commands.entity(entity).remove::<ExternalImpulse>(); //动作停止时清除外部冲量
                     // velocity.linvel = Vec2::new(0.0, 0.0);
-                    info!("remove ExternalImpulse")
+                    debug!("remove ExternalImpulse")
                 }
-                (GameEvent::Hit { uid, direction, attack_action: new_attack_action, hit_action, impulse }, _) => {
+                (GameEvent::Hit { uid, attacker, direction, attack_action: new_attack_action, hit_action, impulse, damage, position }, _) => {
                     if uid != hituid {
                         continue;
                     }
 
                     if let CharacterState::Hit { ref attack_action, ref hit_action } = *state {
-                        info!("current_action_name: {}, new_action_name: {}, == {}", attack_action, new_attack_action, attack_action == new_attack_action);
+                        debug!("current_action_name: {}, new_action_name: {}, == {}", attack_action, new_attack_action, attack_action == new_attack_action);
                         if attack_action == new_attack_action { //同一技能不连续命中
                             continue;
                         }
                     }
 
+                    if health.current <= 0. { //已经倒地
+                        continue;
+                    }
+                    health.current = (health.current - damage).max(0.);
+                    combat_events.send(CombatEvent::Damage {
+                        uid: *uid,
+                        attacker: *attacker,
+                        attack_action: new_attack_action.clone(),
+                        damage: *damage,
+                        position: *position,
+                    });
+                    if health.current <= 0. {
+                        combat_events.send(CombatEvent::KO(*uid));
+                    }
+
                     *state = CharacterState::Hit {
                         attack_action: new_attack_action.clone(),
                         hit_action: hit_action.clone(),
                     };
                     let action_name = state.to_string();
                     let action = characters.get(character_name.as_str()).unwrap().actions.get(&action_name).unwrap();
-                    let texture_atlas = characters_texture_atlas.get(character_name.as_str()).unwrap().get(&action_name).unwrap().clone();
+                    let texture_atlas = characters_texture_atlas.get_action(character_name, palette.0, &action_name).unwrap().clone();
                     *texture = texture_atlas;
-                    info!("uid: {:?}", uid);
+                    debug!("uid: {:?}", uid);
                     set_character_action(sprite, indices, timer, action);
 
                     if let Some(impulse) = impulse {
@@ -318,6 +373,19 @@ fn state(
     }
 }
 
+/// 气够时扣除动作消耗的气, 带定格的动作开始超必杀定格; 气不够时返回 false, 不能发动
+fn spend_meter(meter: &mut Meter, freeze: &mut SuperFreeze, uid: UID, action: &Action) -> bool {
+    if action.meter_cost > meter.current {
+        debug!("uid: {:?} not enough meter: {} < {}", uid, meter.current, action.meter_cost);
+        return false;
+    }
+    meter.current -= action.meter_cost;
+    if action.super_freeze > 0. {
+        freeze.start(uid, action.super_freeze);
+    }
+    true
+}
+
 fn action(
     mut commands: Commands,
     characters: Res<Characters>,
@@ -399,40 +467,58 @@ fn action(
     }
 }
 
+/// 攻击框与受击框重叠时发出 `GameEvent::Hit`.
+/// 防御规则: 攻击框碰到对方的 `Blockbox` 时发出 `CombatEvent::Block`, 这次攻击(同一个动作)直到攻击框消失都不再造成伤害,
+/// 即使之后又碰到了受击框; 被防御的攻击按攻击者记在 `blocked` 中.
 fn damage(
     mut hitbox_query: Query<(&Transform, &Hitbox, &UID, &Direction, &CharacterState, &CharacterName)>,
-    mut hurtbox_query: Query<(&Transform, &Hurtbox, &UID)>,
+    mut hurtbox_query: Query<(&Transform, &Hurtbox, Option<&Blockbox>, &UID)>,
     mut events: EventWriter<GameEvent>,
+    mut combat_events: EventWriter<CombatEvent>,
+    mut blocked: Local<HashMap<UID, String>>,
     characters: Res<Characters>,
 ) {
+    // 被防御的攻击在动作结束(攻击框消失)前只算一次
+    blocked.retain(|uid, attack_action| {
+        hitbox_query.iter().any(|(_, _, hituid, _, character_state, _)| hituid == uid && character_state.to_string() == *attack_action)
+    });
+
     for (hit_transform, hitbox, hituid, direction, character_state, character_name) in hitbox_query.iter_mut() {
-        for (hurt_transform, hurtbox, hurtuid) in hurtbox_query.iter_mut() {
+        for (hurt_transform, hurtbox, blockbox, hurtuid) in hurtbox_query.iter_mut() {
             if hituid != hurtuid {
-                let x = hit_transform.translation.x;
-                let y = hit_transform.translation.y;
-
-                let hit = Rectbox {
-                    min: vec2(x + hitbox.min.x, y + hitbox.min.y),
-                    max: vec2(x + hitbox.max.x, y + hitbox.max.y),
-                };
-
-                let hurt = Rectbox {
-                    min: vec2(hurt_transform.translation.x + hurtbox.min.x, hurt_transform.translation.y + hurtbox.min.y),
-                    max: vec2(hurt_transform.translation.x + hurtbox.max.x, hurt_transform.translation.y + hurtbox.max.y),
-                };
-
-                let x_overlap = hit.min.x <= hurt.max.x && hit.max.x >= hurt.min.x;
-                let y_overlap = hit.min.y <= hurt.max.y && hit.max.y >= hurt.min.y;
-                // info!("hit: {:?}, hurt:{:?}, x_overlap: {}, y_overlap: {}", hit, hurt, x_overlap, y_overlap );
-                if x_overlap && y_overlap {
-                    let action = characters.get(character_name.as_str()).unwrap().actions.get(&character_state.to_string()).unwrap();
-                    info!("attack_action: {}, hit_action: {:?}, ", &character_state.to_string(), action.hit_action);
+                let hit = world_box(hit_transform, hitbox);
+                let attack_action = character_state.to_string();
+
+                // 先打到防御框时算作被防御
+                if let Some(contact) = blockbox.and_then(|blockbox| overlap(&hit, &world_box(hurt_transform, blockbox))) {
+                    if blocked.get(hituid) != Some(&attack_action) {
+                        blocked.insert(*hituid, attack_action.clone());
+                        combat_events.send(CombatEvent::Block {
+                            uid: *hurtuid,
+                            attacker: *hituid,
+                            attack_action,
+                            position: (contact.min + contact.max) / 2.,
+                        });
+                    }
+                    continue;
+                }
+                if blocked.contains_key(hituid) {
+                    continue;
+                }
+
+                // info!("hit: {:?}, hurt:{:?}", hit, world_box(hurt_transform, hurtbox));
+                if let Some(contact) = overlap(&hit, &world_box(hurt_transform, hurtbox)) {
+                    let action = characters.get(character_name.as_str()).unwrap().actions.get(&attack_action).unwrap();
+                    debug!("attack_action: {}, hit_action: {:?}, ", &attack_action, action.hit_action);
                     events.send(GameEvent::Hit {
                         uid: hurtuid.clone(),
+                        attacker: *hituid,
                         direction: *direction,
-                        attack_action: character_state.to_string(),
+                        attack_action,
                         hit_action: action.hit_action.clone().unwrap(),
                         impulse: action.external_impulse,
+                        damage: action.damage,
+                        position: (contact.min + contact.max) / 2.,
                     });
                 }
             }
@@ -440,12 +526,39 @@ fn damage(
     }
 }
 
+/// 判定框在世界坐标中的位置
+fn world_box(transform: &Transform, rect: &Rectbox) -> Rectbox {
+    let origin = transform.translation.truncate();
+    Rectbox {
+        min: origin + rect.min,
+        max: origin + rect.max,
+    }
+}
+
+/// 两个判定框的重叠区域
+fn overlap(a: &Rectbox, b: &Rectbox) -> Option<Rectbox> {
+    let x_overlap = a.min.x <= b.max.x && a.max.x >= b.min.x;
+    let y_overlap = a.min.y <= b.max.y && a.max.y >= b.min.y;
+    (x_overlap && y_overlap).then(|| Rectbox {
+        min: a.min.max(b.min),
+        max: a.max.min(b.max),
+    })
+}
+
 fn animation(
     time: Res<Time>,
+    freeze: Res<SuperFreeze>,
+    hitstop: Res<Hitstop>,
     mut events: EventWriter<GameEvent>,
     mut query: Query<(&UID, &AnimationIndices, &mut AnimationTimer, &mut TextureAtlasSprite)>,
 ) {
+    if hitstop.active() {
+        return;
+    }
     for (uid, indices, mut timer, mut sprite) in &mut query {
+        if freeze.is_frozen(*uid) {
+            continue;
+        }
         if indices.repeat == false && sprite.index == indices.last {
             events.send(GameEvent::Stop(uid.clone()));
             continue;
@@ -461,6 +574,24 @@ fn animation(
     }
 }
 
+/// 按当前帧的锚点对齐精灵, 帧尺寸不同或画面偏心时精灵不会相对碰撞体和判定框漂移
+fn anchor(
+    characters: Res<Characters>,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+    mut query: Query<(&CharacterState, &CharacterName, &Handle<TextureAtlas>, &mut TextureAtlasSprite)>,
+) {
+    for (state, character_name, texture_atlas, mut sprite) in query.iter_mut() {
+        let action = characters.get(character_name.as_str()).unwrap().actions.get(&state.to_string()).unwrap();
+        let (Some(frame), Some(texture_atlas)) = (action.frames.get(sprite.index), texture_atlases.get(texture_atlas)) else {
+            continue;
+        };
+        let Some(rect) = texture_atlas.textures.get(sprite.index) else {
+            continue;
+        };
+        sprite.anchor = frame.sprite_anchor(rect.size(), sprite.flip_x);
+    }
+}
+
 // fn movement(
 //     time: Res<Time>,
 //     mut query: Query<(&mut Transform, &Velocity)>,
@@ -472,66 +603,13 @@ fn animation(
 //     }
 // }
 
-fn hurtbox(
-    mut query: Query<(&Transform, &Hurtbox, Option<&Hitbox>, Option<&Blockbox>)>,
-    mut gizmos: Gizmos,
-) {
-    for (transform, hurtbox, hitbox, blockbox) in query.iter_mut() {
-        let x = transform.translation.x;
-        let y = transform.translation.y;
-
-        let p_x = x + hurtbox.min.x + (hurtbox.max.x - hurtbox.min.x) / 2.;
-        let p_y = y + hurtbox.min.y + (hurtbox.max.y - hurtbox.min.y) / 2.;
-
-        let position = vec2(p_x, p_y);
-        let size = vec2(hurtbox.max.x - hurtbox.min.x, hurtbox.max.y - hurtbox.min.y);
-        // info!("position: {}, size: {}", position, size);
-        gizmos.rect_2d(
-            position,
-            0.,
-            size,
-            Color::BLUE,
-        );
-
-        if let Some(hitbox) = hitbox {
-            let p_x = x + hitbox.min.x + (hitbox.max.x - hitbox.min.x) / 2.;
-            let p_y = y + hitbox.min.y + (hitbox.max.y - hitbox.min.y) / 2.;
-
-            let position = vec2(p_x, p_y);
-            let size = vec2(hitbox.max.x - hitbox.min.x, hitbox.max.y - hitbox.min.y);
-            // info!("position: {}, size: {}", position, size);
-            gizmos.rect_2d(
-                position,
-                0.,
-                size,
-                Color::RED,
-            );
-        }
-
-        if let Some(blockbox) = blockbox {
-            let p_x = x + blockbox.min.x + (blockbox.max.x - blockbox.min.x) / 2.;
-            let p_y = y + blockbox.min.y + (blockbox.max.y - blockbox.min.y) / 2.;
-
-            let position = vec2(p_x, p_y);
-            let size = vec2(blockbox.max.x - blockbox.min.x, blockbox.max.y - blockbox.min.y);
-            // info!("position: {}, size: {}", position, size);
-            gizmos.rect_2d(
-                position,
-                0.,
-                size,
-                Color::WHITE,
-            );
-        }
-    }
-}
-
 fn set_character_action(
     mut sprite: Mut<TextureAtlasSprite>,
     mut indices: Mut<AnimationIndices>,
     mut timer: Mut<AnimationTimer>,
     mut action: &Action,
 ) {
-    info!("set action: {:?}", action);
+    debug!("set action: {:?}", action);
 
     sprite.index = 0;
     *indices = AnimationIndices {
@@ -0,0 +1,326 @@
+//! 声音: 每个场地循环播放一首背景音乐, 动作数据中配置的逐帧音效和命中/防御音效.
+//! 音量分为 总音量 / 音乐 / 音效 三路, 任意界面按 Esc 打开音量设置, 上下选择, 左右调整,
+//! 关闭时写入 `settings.ron`, 下次启动时读取. 设置面板打开时不响应选人/选场地/对战输入.
+
+use std::collections::HashMap;
+use bevy::audio::{AudioSink, AudioSinkPlayback, PlaybackSettings, Volume};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::{CharacterName, CharacterState, CombatEvent, GameState, MyAssets, UID};
+use crate::loading::Characters;
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<VolumeSettings>()
+            .init_resource::<Music>()
+            .init_resource::<SettingsMenu>()
+            .register_type::<VolumeSettings>()
+            .add_systems(Startup, load_settings)
+            .add_systems(Update, (settings_input, update_settings_menu).chain())
+            .add_systems(Update, (play_music, apply_music_volume))
+            .add_systems(Update, (frame_sounds, combat_sounds).run_if(in_state(GameState::Playing)))
+            .add_systems(OnExit(GameState::Playing), stop_music);
+    }
+}
+
+/// 音量, 0..1; 实际音量为总音量乘以分路音量
+#[derive(Resource, Reflect, Serialize, Deserialize, Clone, Copy)]
+#[reflect(Resource)]
+pub struct VolumeSettings {
+    pub master: f32,
+    pub music: f32,
+    pub sfx: f32,
+}
+
+impl Default for VolumeSettings {
+    fn default() -> Self {
+        Self {
+            master: 1.,
+            music: 0.6,
+            sfx: 0.8,
+        }
+    }
+}
+
+impl VolumeSettings {
+    pub fn music_volume(&self) -> f32 {
+        (self.master * self.music).clamp(0., 1.)
+    }
+
+    pub fn sfx_volume(&self) -> f32 {
+        (self.master * self.sfx).clamp(0., 1.)
+    }
+}
+
+/// 每次按键调整的音量
+const VOLUME_STEP: f32 = 0.1;
+
+/// 音量设置文件, 放在可执行文件目录(开发时为工程根目录)下
+const SETTINGS_FILE: &str = "settings.ron";
+
+/// 音量设置面板, `cursor` 为当前选中的那一路
+#[derive(Resource, Default)]
+pub struct SettingsMenu {
+    pub open: bool,
+    cursor: usize,
+}
+
+/// 设置面板未打开时才处理游戏输入
+pub fn settings_closed(menu: Res<SettingsMenu>) -> bool {
+    !menu.open
+}
+
+#[derive(Component)]
+struct SettingsRoot;
+
+#[derive(Component)]
+struct SettingsText;
+
+/// 当前背景音乐路径, 进入场地时按场地数据设置, 为空时不播放
+#[derive(Resource, Deref, DerefMut, Default)]
+pub struct Music(pub Option<String>);
+
+/// 正在播放的背景音乐
+#[derive(Component)]
+struct MusicTrack(String);
+
+/// 在对战中播放一次音效
+fn play_sfx(commands: &mut Commands, asset_server: &AssetServer, volume: &VolumeSettings, path: &str) {
+    commands.spawn(AudioBundle {
+        source: asset_server.load(path),
+        settings: PlaybackSettings::DESPAWN.with_volume(Volume::new_relative(volume.sfx_volume())),
+    });
+}
+
+/// 背景音乐切换时停掉旧的, 循环播放新的
+fn play_music(
+    mut commands: Commands,
+    music: Res<Music>,
+    volume: Res<VolumeSettings>,
+    asset_server: Res<AssetServer>,
+    game_state: Res<State<GameState>>,
+    tracks: Query<(Entity, &MusicTrack)>,
+) {
+    // 加载和选人界面没有音乐
+    let path = match game_state.get() {
+        GameState::Playing => music.as_deref(),
+        _ => None,
+    };
+    if tracks.iter().any(|(_, track)| Some(track.0.as_str()) == path) {
+        return;
+    }
+    for (entity, _) in tracks.iter() {
+        commands.entity(entity).despawn();
+    }
+    if let Some(path) = path {
+        commands.spawn((
+            AudioBundle {
+                source: asset_server.load(path),
+                settings: PlaybackSettings::LOOP.with_volume(Volume::new_relative(volume.music_volume())),
+            },
+            MusicTrack(path.to_string()),
+        ));
+    }
+}
+
+fn apply_music_volume(volume: Res<VolumeSettings>, sinks: Query<&AudioSink, With<MusicTrack>>) {
+    if !volume.is_changed() {
+        return;
+    }
+    for sink in sinks.iter() {
+        sink.set_volume(volume.music_volume());
+    }
+}
+
+fn stop_music(mut commands: Commands, tracks: Query<Entity, With<MusicTrack>>) {
+    for entity in tracks.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// 角色播放到配置了音效的帧时播放一次
+fn frame_sounds(
+    mut commands: Commands,
+    mut last_frames: Local<HashMap<Entity, (String, usize)>>,
+    asset_server: Res<AssetServer>,
+    volume: Res<VolumeSettings>,
+    characters: Res<Characters>,
+    fighters: Query<(Entity, &CharacterState, &CharacterName, &TextureAtlasSprite)>,
+) {
+    last_frames.retain(|entity, _| fighters.contains(*entity));
+    for (entity, state, character_name, sprite) in fighters.iter() {
+        let current = (state.to_string(), sprite.index);
+        if last_frames.get(&entity) == Some(&current) {
+            continue;
+        }
+        let sound = characters
+            .get(character_name.as_str())
+            .and_then(|character| character.actions.get(&current.0))
+            .and_then(|action| action.frames.get(current.1))
+            .and_then(|frame| frame.sound.as_deref());
+        if let Some(sound) = sound {
+            play_sfx(&mut commands, &asset_server, &volume, sound);
+        }
+        last_frames.insert(entity, current);
+    }
+}
+
+fn combat_sounds(
+    mut commands: Commands,
+    mut events: EventReader<CombatEvent>,
+    asset_server: Res<AssetServer>,
+    volume: Res<VolumeSettings>,
+    characters: Res<Characters>,
+    fighters: Query<(&UID, &CharacterName)>,
+) {
+    for event in events.iter() {
+        let (attacker, attack_action, blocked) = match event {
+            CombatEvent::Damage { attacker, attack_action, .. } => (attacker, attack_action, false),
+            CombatEvent::Block { attacker, attack_action, .. } => (attacker, attack_action, true),
+            _ => continue,
+        };
+        let action = fighters
+            .iter()
+            .find(|(uid, _)| *uid == attacker)
+            .and_then(|(_, character_name)| characters.get(character_name.as_str()))
+            .and_then(|character| character.actions.get(attack_action));
+        let sound = action.and_then(|action| if blocked { action.block_sound.as_deref() } else { action.hit_sound.as_deref() });
+        if let Some(sound) = sound {
+            play_sfx(&mut commands, &asset_server, &volume, sound);
+        }
+    }
+}
+
+#[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+fn settings_path() -> std::path::PathBuf {
+    bevy::asset::FileAssetIo::get_base_path().join(SETTINGS_FILE)
+}
+
+/// 读取上次保存的音量, 没有或读取失败时使用默认值
+#[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+fn load_settings(mut volume: ResMut<VolumeSettings>) {
+    let path = settings_path();
+    let Ok(content) = std::fs::read_to_string(&path) else {
+        return;
+    };
+    match ron::de::from_str::<VolumeSettings>(&content) {
+        Ok(settings) => *volume = settings,
+        Err(err) => warn!("读取音量设置失败 {}: {err}", path.display()),
+    }
+}
+
+#[cfg(any(target_arch = "wasm32", target_os = "android"))]
+fn load_settings() {}
+
+#[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+fn save_settings(volume: &VolumeSettings) {
+    let path = settings_path();
+    let result = ron::ser::to_string_pretty(volume, ron::ser::PrettyConfig::default())
+        .map_err(|err| err.to_string())
+        .and_then(|content| std::fs::write(&path, content).map_err(|err| err.to_string()));
+    if let Err(err) = result {
+        warn!("保存音量设置失败 {}: {err}", path.display());
+    }
+}
+
+#[cfg(any(target_arch = "wasm32", target_os = "android"))]
+fn save_settings(_volume: &VolumeSettings) {}
+
+/// Esc 打开/关闭设置面板, 打开时上下选择, 左右调整; 关闭时保存
+fn settings_input(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    my_assets: Option<Res<MyAssets>>,
+    mut menu: ResMut<SettingsMenu>,
+    mut volume: ResMut<VolumeSettings>,
+    roots: Query<Entity, With<SettingsRoot>>,
+) {
+    // 字体加载完之前不能打开
+    let Some(my_assets) = my_assets else {
+        return;
+    };
+    if keys.just_pressed(KeyCode::Escape) {
+        menu.open = !menu.open;
+        if menu.open {
+            spawn_settings_menu(&mut commands, &my_assets.font);
+        } else {
+            for entity in roots.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+            save_settings(&volume);
+        }
+        return;
+    }
+    if !menu.open {
+        return;
+    }
+    if keys.just_pressed(KeyCode::Up) {
+        menu.cursor = (menu.cursor + 2) % 3;
+    }
+    if keys.just_pressed(KeyCode::Down) {
+        menu.cursor = (menu.cursor + 1) % 3;
+    }
+    let delta = if keys.just_pressed(KeyCode::Left) {
+        -VOLUME_STEP
+    } else if keys.just_pressed(KeyCode::Right) {
+        VOLUME_STEP
+    } else {
+        return;
+    };
+    let value = match menu.cursor {
+        0 => &mut volume.master,
+        1 => &mut volume.music,
+        _ => &mut volume.sfx,
+    };
+    // 按步长取整, 避免累加误差
+    *value = ((*value + delta) / VOLUME_STEP).round().clamp(0., 1. / VOLUME_STEP) * VOLUME_STEP;
+}
+
+fn spawn_settings_menu(commands: &mut Commands, font: &Handle<Font>) {
+    let text_style = TextStyle {
+        font: font.clone(),
+        font_size: 32.,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.6).into(),
+                z_index: ZIndex::Global(100),
+                ..default()
+            },
+            SettingsRoot,
+            Name::new("Settings"),
+        ))
+        .with_children(|root| {
+            root.spawn((TextBundle::from_section("", text_style), SettingsText));
+        });
+}
+
+fn update_settings_menu(
+    menu: Res<SettingsMenu>,
+    volume: Res<VolumeSettings>,
+    mut texts: Query<&mut Text, With<SettingsText>>,
+) {
+    // 游戏内字体不含中文, 标签用英文
+    let rows = [("MASTER", volume.master), ("MUSIC", volume.music), ("SFX", volume.sfx)];
+    for mut text in texts.iter_mut() {
+        let mut content = "VOLUME\n\n".to_string();
+        for (i, (label, value)) in rows.iter().enumerate() {
+            let cursor = if i == menu.cursor { ">" } else { " " };
+            content += &format!("{cursor} {label:<6} {:>3}%\n", (value * 100.).round());
+        }
+        content += "\nUP/DOWN SELECT  LEFT/RIGHT ADJUST  ESC CLOSE";
+        text.sections[0].value = content;
+    }
+}
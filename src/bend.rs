@@ -0,0 +1,193 @@
+//! 压草: 角色、飞行道具和自由相机附近的草叶被推开, 离开后慢慢立起来.
+//! 带 `Bender` 的实体每帧记录位置, 移动经过的位置留下逐渐减弱的痕迹, 最强的 `MAX_BENDERS` 个写进 `Benders`.
+//! `Benders` 提取到渲染世界后写进同一个 `BendBuffer`, 所有 `CustomMaterial` 的绑定组共用这个缓冲.
+
+use bevy::prelude::*;
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+use bevy::render::render_resource::encase::StorageBuffer;
+use bevy::render::render_resource::{Buffer, BufferDescriptor, BufferUsages, ShaderSize};
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+use bevy::render::{Render, RenderApp, RenderSet};
+use bevy::utils::HashMap;
+use crate::billboard::CombatPlane;
+use crate::tools::CameraController;
+use crate::{GameState, UID};
+
+/// 着色器中 `benders` 数组的长度, 与 `shaders/bend.wgsl` 一致
+pub const MAX_BENDERS: usize = 16;
+
+pub struct BendPlugin;
+
+impl Plugin for BendPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BendSettings>()
+            .init_resource::<BendTrail>()
+            .init_resource::<Benders>()
+            .register_type::<Bender>()
+            .register_type::<BendSettings>()
+            .add_systems(Update, tag_fighters.run_if(in_state(GameState::Playing)))
+            .add_systems(PostUpdate, (update_trail, apply_benders).chain().after(bevy::transform::TransformSystem::TransformPropagate))
+            .add_plugins(ExtractResourcePlugin::<Benders>::default());
+
+        app.sub_app_mut(RenderApp)
+            .add_systems(Render, write_benders.in_set(RenderSet::Prepare));
+    }
+
+    fn finish(&self, app: &mut App) {
+        let buffer = BendBuffer(app.world.resource::<RenderDevice>().create_buffer(&BufferDescriptor {
+            label: Some("bend buffer"),
+            size: <[BenderUniform; MAX_BENDERS]>::SHADER_SIZE.get(),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }));
+        app.sub_app_mut(RenderApp).insert_resource(buffer.clone());
+        app.insert_resource(buffer);
+    }
+}
+
+/// 推开附近的草叶
+#[derive(Component, Reflect, Clone, Copy)]
+#[reflect(Component)]
+pub struct Bender {
+    /// 影响半径(米)
+    pub radius: f32,
+    /// 叶尖被推开的最大距离(米)
+    pub strength: f32,
+    /// 位置是 2D 战斗坐标(像素), 需要经 `CombatPlane` 换算到 3D
+    pub in_plane: bool,
+}
+
+impl Default for Bender {
+    fn default() -> Self {
+        Self { radius: 0.6, strength: 0.3, in_plane: false }
+    }
+}
+
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct BendSettings {
+    /// 草叶从完全压倒到重新立起的时间(秒)
+    pub recovery: f32,
+    /// 移动超过这个距离(米)才留下新的痕迹
+    pub trail_spacing: f32,
+}
+
+impl Default for BendSettings {
+    fn default() -> Self {
+        Self { recovery: 1.5, trail_spacing: 0.3 }
+    }
+}
+
+pub use uniform::BenderUniform;
+
+// `ShaderType` 派生宏为每个字段生成的检查函数不会被调用, 放在单独的模块里关掉 dead_code
+#[allow(dead_code)]
+mod uniform {
+    use bevy::prelude::*;
+    use bevy::render::render_resource::ShaderType;
+
+    /// 着色器中的 `Bender`; `radius` 为 0 的槽位不起作用
+    #[derive(ShaderType, Clone, Copy, Debug, Default, PartialEq)]
+    pub struct BenderUniform {
+        pub position: Vec3,
+        pub radius: f32,
+        pub strength: f32,
+    }
+}
+
+/// 本帧起作用的 `Bender`, 对应着色器中的 `benders`
+#[derive(Resource, ExtractResource, Clone, Default, PartialEq, Deref, DerefMut)]
+pub struct Benders([BenderUniform; MAX_BENDERS]);
+
+/// 所有 `CustomMaterial` 共用的 `benders` 缓冲, 主世界和渲染世界各有一份句柄
+#[derive(Resource, Clone, Deref)]
+pub struct BendBuffer(Buffer);
+
+struct TrailPoint {
+    position: Vec3,
+    radius: f32,
+    strength: f32,
+    age: f32,
+}
+
+/// 压草的痕迹, 以及每个 `Bender` 最后留下痕迹的位置
+#[derive(Resource, Default)]
+struct BendTrail {
+    points: Vec<TrailPoint>,
+    last: HashMap<Entity, Vec3>,
+    current: Vec<BenderUniform>,
+}
+
+
+fn tag_fighters(mut commands: Commands, fighters: Query<Entity, (With<UID>, Without<Bender>)>) {
+    for entity in fighters.iter() {
+        commands.entity(entity).insert(Bender { in_plane: true, ..default() });
+    }
+}
+
+fn update_trail(
+    time: Res<Time>,
+    settings: Res<BendSettings>,
+    plane: Res<CombatPlane>,
+    mut trail: ResMut<BendTrail>,
+    benders: Query<(Entity, &Bender, &GlobalTransform, Option<&CameraController>)>,
+) {
+    let trail = trail.as_mut();
+    let dt = time.delta_seconds();
+    trail.points.retain_mut(|point| {
+        point.age += dt;
+        point.age < settings.recovery
+    });
+
+    trail.current.clear();
+    let mut alive = Vec::new();
+    for (entity, bender, transform, controller) in benders.iter() {
+        // 相机只有切换为自由相机时才压草
+        if controller.is_some_and(|controller| !controller.enabled) {
+            continue;
+        }
+        let position = if bender.in_plane {
+            plane.to_world(transform.translation().truncate())
+        } else {
+            transform.translation()
+        };
+        alive.push(entity);
+        trail.current.push(BenderUniform { position, radius: bender.radius, strength: bender.strength });
+
+        let moved = trail.last.get(&entity).is_none_or(|last| last.distance(position) >= settings.trail_spacing);
+        if moved {
+            trail.last.insert(entity, position);
+            trail.points.push(TrailPoint { position, radius: bender.radius, strength: bender.strength, age: 0. });
+        }
+    }
+    trail.last.retain(|entity, _| alive.contains(entity));
+}
+
+/// 当前的 `Bender` 优先, 剩余槽位留给最新的痕迹
+fn apply_benders(
+    settings: Res<BendSettings>,
+    trail: Res<BendTrail>,
+    mut current: ResMut<Benders>,
+) {
+    let mut benders = Benders::default();
+    let recovery = settings.recovery.max(0.01);
+    let trail_points = trail.points.iter().rev().map(|point| BenderUniform {
+        position: point.position,
+        radius: point.radius,
+        strength: point.strength * (1. - point.age / recovery),
+    });
+    for (slot, bender) in benders.iter_mut().zip(trail.current.iter().copied().chain(trail_points)) {
+        *slot = bender;
+    }
+    // 没有变化时不再提取和上传
+    current.set_if_neq(benders);
+}
+
+fn write_benders(benders: Res<Benders>, buffer: Res<BendBuffer>, queue: Res<RenderQueue>) {
+    if !benders.is_changed() {
+        return;
+    }
+    let mut data = StorageBuffer::new(Vec::new());
+    data.write(&benders.0).unwrap();
+    queue.write_buffer(&buffer, 0, data.as_ref());
+}
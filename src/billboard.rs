@@ -0,0 +1,180 @@
+//! 2.5D 模式: 战斗仍然在 2D 物理中模拟, 角色以 `AtlasSprite3d` 公告板的形式画在 3D 场景中的战斗平面上.
+//! 2D 坐标(像素)按 `CombatPlane` 映射到 3D 世界, V 键在 2D / 2.5D 之间切换.
+//! 公告板使用角色当前的图集, 与 2D 精灵一样是按调色板换色后的图集.
+
+use bevy::prelude::*;
+use bevy_sprite3d::{AtlasSprite3d, AtlasSprite3dComponent, Sprite3dParams};
+use crate::{CharacterName, CharacterState, FlatCamera, GameState, UID};
+use crate::loading::Characters;
+
+pub struct BillboardPlugin;
+
+impl Plugin for BillboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FightView>()
+            .init_resource::<CombatPlane>()
+            .register_type::<FightView>()
+            .register_type::<CombatPlane>()
+            .add_systems(Update, toggle_view)
+            .add_systems(Update, (spawn_billboards, sync_view, update_billboards).chain().run_if(in_state(GameState::Playing)));
+    }
+}
+
+#[derive(Resource, Reflect, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[reflect(Resource)]
+pub enum FightView {
+    /// 2D 相机渲染精灵
+    Flat,
+    /// 3D 相机渲染战斗平面上的公告板
+    #[default]
+    Billboard,
+}
+
+/// 2D 战斗坐标到 3D 场景的映射: 2D 的 x/y 对应 3D 的 x/y, 平面位于 z = origin.z
+#[derive(Resource, Reflect, Clone, Copy, Debug)]
+#[reflect(Resource)]
+pub struct CombatPlane {
+    /// 2D 坐标原点在 3D 中的位置
+    pub origin: Vec3,
+    pub pixels_per_metre: f32,
+}
+
+impl Default for CombatPlane {
+    fn default() -> Self {
+        Self {
+            // 2D 地面顶部 y = -230, 对齐到 3D 场景的 y = 0
+            origin: Vec3::new(0., 2.3, 0.),
+            pixels_per_metre: 100.,
+        }
+    }
+}
+
+impl CombatPlane {
+    pub fn to_world(&self, position: Vec2) -> Vec3 {
+        self.origin + position.extend(0.) / self.pixels_per_metre
+    }
+
+    /// `to_world` 的逆变换, 3D 坐标投影到战斗平面上, 忽略 z
+    pub fn from_world(&self, position: Vec3) -> Vec2 {
+        (position - self.origin).truncate() * self.pixels_per_metre
+    }
+
+    pub fn to_world_size(&self, size: Vec2) -> Vec2 {
+        size / self.pixels_per_metre
+    }
+}
+
+/// 角色在 3D 场景中的公告板, 跟随 `fighter` 的位置和动画帧
+#[derive(Component)]
+pub struct Billboard {
+    pub fighter: Entity,
+    atlas: Option<Handle<TextureAtlas>>,
+}
+
+fn toggle_view(input: Res<Input<KeyCode>>, mut view: ResMut<FightView>) {
+    if input.just_pressed(KeyCode::V) {
+        *view = match *view {
+            FightView::Flat => FightView::Billboard,
+            FightView::Billboard => FightView::Flat,
+        };
+    }
+}
+
+fn spawn_billboards(
+    mut commands: Commands,
+    query: Query<Entity, Added<UID>>,
+) {
+    for fighter in query.iter() {
+        commands.spawn((
+            Billboard { fighter, atlas: None },
+            SpatialBundle::default(),
+            Name::new("Billboard"),
+        ));
+    }
+}
+
+/// 2D 相机和 2D 精灵只在 Flat 模式下显示
+fn sync_view(
+    view: Res<FightView>,
+    mut cameras: Query<&mut Camera, With<FlatCamera>>,
+    mut fighters: Query<&mut Visibility, (With<UID>, With<TextureAtlasSprite>)>,
+) {
+    let flat = *view == FightView::Flat;
+    for mut camera in cameras.iter_mut() {
+        if camera.is_active != flat {
+            camera.is_active = flat;
+        }
+    }
+    let visibility = if flat { Visibility::Inherited } else { Visibility::Hidden };
+    for mut fighter_visibility in fighters.iter_mut() {
+        if *fighter_visibility != visibility {
+            *fighter_visibility = visibility;
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn update_billboards(
+    mut commands: Commands,
+    view: Res<FightView>,
+    plane: Res<CombatPlane>,
+    characters: Res<Characters>,
+    mut sprite_params: Sprite3dParams,
+    fighters: Query<(&Transform, &Handle<TextureAtlas>, &TextureAtlasSprite, &CharacterState, &CharacterName), Without<Billboard>>,
+    mut billboards: Query<(Entity, &mut Billboard, &mut Transform, &mut Visibility, Option<&mut AtlasSprite3dComponent>)>,
+) {
+    for (entity, mut billboard, mut transform, mut visibility, sprite3d) in billboards.iter_mut() {
+        let Ok((fighter_transform, atlas, sprite, state, character_name)) = fighters.get(billboard.fighter) else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+
+        // 公告板网格以帧中心为原点, 按帧锚点平移; 朝左时沿 x 镜像
+        let frame_size = sprite_params
+            .atlases
+            .get(atlas)
+            .and_then(|texture_atlas| texture_atlas.textures.get(sprite.index))
+            .map(|rect| rect.size())
+            .unwrap_or(Vec2::ZERO);
+        let character = characters.get(character_name.as_str());
+        let anchor = character
+            .and_then(|character| character.actions.get(&state.to_string()))
+            .and_then(|action| action.frames.get(sprite.index))
+            .map(|frame| frame.sprite_anchor(frame_size, sprite.flip_x).as_vec())
+            .unwrap_or(Vec2::ZERO);
+        let position = fighter_transform.translation.truncate() - anchor * frame_size;
+        transform.translation = plane.to_world(position);
+        transform.scale.x = if sprite.flip_x { -1. } else { 1. };
+
+        // 切换动作时换成新动作的图集, 图片未加载完成时等待
+        if billboard.atlas.as_ref() != Some(atlas) {
+            let Some(texture) = sprite_params.atlases.get(atlas).map(|texture_atlas| texture_atlas.texture.clone()) else {
+                continue;
+            };
+            // 换色后的贴图不经过 AssetServer, 只检查是否已在 `Assets<Image>` 中
+            if sprite_params.images.get(&texture).is_none() {
+                continue;
+            }
+            commands.entity(entity).insert(AtlasSprite3d {
+                atlas: atlas.clone(),
+                pixels_per_metre: plane.pixels_per_metre,
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                double_sided: true,
+                index: sprite.index,
+                transform: *transform,
+                ..default()
+            }.bundle(&mut sprite_params));
+            billboard.atlas = Some(atlas.clone());
+        } else if let Some(mut sprite3d) = sprite3d {
+            if sprite3d.index != sprite.index && sprite.index < sprite3d.atlas.len() {
+                sprite3d.index = sprite.index;
+            }
+        }
+
+        let billboard_visibility = if *view == FightView::Billboard { Visibility::Inherited } else { Visibility::Hidden };
+        if *visibility != billboard_visibility {
+            *visibility = billboard_visibility;
+        }
+    }
+}
@@ -0,0 +1,195 @@
+//! 对战相机: 跟随两名角色的中点, 角色拉开距离时拉远镜头, 镜头不超出场地边界, 平滑过渡.
+//! 场地边界优先取场地墙壁, 没有墙壁时取 glTF 场景的包围盒.
+//! F9 切换到自由相机 `CameraController` 调试, 再按一次回到对战相机.
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use crate::{FlatCamera, GameState, MainCamera, SceneBounds, UID, Wall};
+use crate::billboard::CombatPlane;
+use crate::tools::{CameraController, CameraControllerPlugin};
+
+pub struct FightCameraPlugin;
+
+impl Plugin for FightCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FightCamera>()
+            .register_type::<FightCamera>()
+            .add_plugins(CameraControllerPlugin)
+            .add_systems(Update, toggle_free_camera)
+            .add_systems(
+                Update,
+                (track_fighters_3d, track_fighters_2d)
+                    .run_if(in_state(GameState::Playing).and_then(|camera: Res<FightCamera>| camera.enabled)),
+            );
+    }
+}
+
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct FightCamera {
+    /// 关闭时由自由相机控制
+    pub enabled: bool,
+    /// 相机相对注视点的方向
+    pub direction: Vec3,
+    /// 相机到注视点的距离范围(米)
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// 角色到画面边缘至少保留的距离(像素)
+    pub margin: f32,
+    /// 注视点在角色中点上方的高度(像素)
+    pub height: f32,
+    /// 越大跟得越紧
+    pub smoothing: f32,
+    /// 2D 相机的缩放范围
+    pub min_zoom: f32,
+    pub max_zoom: f32,
+}
+
+impl Default for FightCamera {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            // 与初始的主相机位置 (0, 15, 34) 看向原点一致
+            direction: Vec3::new(0., 15., 34.).normalize(),
+            min_distance: 6.,
+            max_distance: 20.,
+            margin: 150.,
+            height: 100.,
+            smoothing: 4.,
+            min_zoom: 1.,
+            max_zoom: 1.6,
+        }
+    }
+}
+
+/// 平滑系数, 与帧率无关
+fn ease(smoothing: f32, time: &Time) -> f32 {
+    1. - (-smoothing * time.delta_seconds()).exp()
+}
+
+fn toggle_free_camera(
+    input: Res<Input<KeyCode>>,
+    mut fight_camera: ResMut<FightCamera>,
+    mut controllers: Query<&mut CameraController, With<MainCamera>>,
+) {
+    if !input.just_pressed(KeyCode::F9) {
+        return;
+    }
+    fight_camera.enabled = !fight_camera.enabled;
+    for mut controller in controllers.iter_mut() {
+        controller.enabled = !fight_camera.enabled;
+        // 从对战相机当前的朝向开始
+        controller.initialized = false;
+    }
+}
+
+/// 2D 战斗坐标中的场地左右边界
+fn stage_bounds(
+    walls: &Query<(&Transform, &Collider), With<Wall>>,
+    scene_bounds: Option<&SceneBounds>,
+    plane: &CombatPlane,
+) -> Option<(f32, f32)> {
+    let mut edges: Vec<(f32, f32)> = walls
+        .iter()
+        .map(|(transform, collider)| {
+            let aabb = collider.raw.compute_local_aabb();
+            (transform.translation.x + aabb.mins.x, transform.translation.x + aabb.maxs.x)
+        })
+        .collect();
+    edges.sort_by(|a, b| a.0.total_cmp(&b.0));
+    match edges.as_slice() {
+        // 墙壁内侧
+        [left, .., right] => Some((left.1, right.0)),
+        _ => scene_bounds.map(|bounds| {
+            let min = (bounds.min().x - plane.origin.x) * plane.pixels_per_metre;
+            let max = (bounds.max().x - plane.origin.x) * plane.pixels_per_metre;
+            (min, max)
+        }),
+    }
+}
+
+/// 注视点不超出边界, 视野比场地宽时居中
+fn clamp_to_bounds(x: f32, half_width: f32, bounds: Option<(f32, f32)>) -> f32 {
+    match bounds {
+        Some((min, max)) if max - min > half_width * 2. => x.clamp(min + half_width, max - half_width),
+        Some((min, max)) => (min + max) / 2.,
+        None => x,
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn track_fighters_3d(
+    time: Res<Time>,
+    settings: Res<FightCamera>,
+    plane: Res<CombatPlane>,
+    scene_bounds: Option<Res<SceneBounds>>,
+    walls: Query<(&Transform, &Collider), With<Wall>>,
+    fighters: Query<&Transform, (With<UID>, Without<MainCamera>)>,
+    mut cameras: Query<(&mut Transform, &Projection), (With<MainCamera>, Without<UID>, Without<Wall>)>,
+) {
+    let Some((min_x, max_x, center_y)) = fighters_extent(fighters.iter()) else {
+        return;
+    };
+    let Ok((mut transform, projection)) = cameras.get_single_mut() else {
+        return;
+    };
+    let Projection::Perspective(perspective) = projection else {
+        return;
+    };
+
+    // 拉远到两名角色加上边距都在画面内
+    let half_width_px = (max_x - min_x) / 2. + settings.margin;
+    let half_width = half_width_px / plane.pixels_per_metre;
+    let half_fov_tan = (perspective.fov / 2.).tan() * perspective.aspect_ratio;
+    let distance = (half_width / half_fov_tan).clamp(settings.min_distance, settings.max_distance);
+
+    let visible_half_width_px = distance * half_fov_tan * plane.pixels_per_metre;
+    let bounds = stage_bounds(&walls, scene_bounds.as_deref(), &plane);
+    let x = clamp_to_bounds((min_x + max_x) / 2., visible_half_width_px, bounds);
+    let target = plane.to_world(Vec2::new(x, center_y + settings.height));
+
+    let goal = target + settings.direction * distance;
+    let t = ease(settings.smoothing, &time);
+    transform.translation = transform.translation.lerp(goal, t);
+    let look = transform.looking_at(target, Vec3::Y).rotation;
+    transform.rotation = transform.rotation.slerp(look, t);
+}
+
+#[allow(clippy::type_complexity)]
+fn track_fighters_2d(
+    time: Res<Time>,
+    settings: Res<FightCamera>,
+    plane: Res<CombatPlane>,
+    scene_bounds: Option<Res<SceneBounds>>,
+    walls: Query<(&Transform, &Collider), With<Wall>>,
+    fighters: Query<&Transform, (With<UID>, Without<FlatCamera>)>,
+    mut cameras: Query<(&mut Transform, &mut OrthographicProjection, &Camera), (With<FlatCamera>, Without<UID>, Without<Wall>)>,
+) {
+    let Some((min_x, max_x, _)) = fighters_extent(fighters.iter()) else {
+        return;
+    };
+    let Ok((mut transform, mut projection, camera)) = cameras.get_single_mut() else {
+        return;
+    };
+    let Some(viewport_size) = camera.logical_viewport_size() else {
+        return;
+    };
+
+    let wanted = (max_x - min_x + settings.margin * 2.) / viewport_size.x;
+    let t = ease(settings.smoothing, &time);
+    projection.scale += (wanted.clamp(settings.min_zoom, settings.max_zoom) - projection.scale) * t;
+
+    let visible_half_width = viewport_size.x * projection.scale / 2.;
+    let bounds = stage_bounds(&walls, scene_bounds.as_deref(), &plane);
+    let x = clamp_to_bounds((min_x + max_x) / 2., visible_half_width, bounds);
+    transform.translation.x += (x - transform.translation.x) * t;
+}
+
+/// 所有角色的 x 范围和平均高度
+fn fighters_extent<'a>(fighters: impl Iterator<Item = &'a Transform>) -> Option<(f32, f32, f32)> {
+    let (count, min_x, max_x, sum_y) = fighters.fold((0, f32::MAX, f32::MIN, 0.), |(count, min_x, max_x, sum_y), transform| {
+        let position = transform.translation;
+        (count + 1, min_x.min(position.x), max_x.max(position.x), sum_y + position.y)
+    });
+    (count > 0).then(|| (min_x, max_x, sum_y / count as f32))
+}
@@ -0,0 +1,633 @@
+//! 草地: `GrassField` 加在网格实体上, 在网格朝上的表面按面积均匀撒草叶.
+//! 所有草叶共用一个草叶网格和一个 `CustomMaterial`, 每块草地只有一次实例化绘制, 随风摇摆(见 `wind`)在顶点着色器中计算.
+//! 草叶受场景光照影响, 并向平行光投射阴影, 阴影预渲染使用同样的摇摆.
+//! 场地数据的 `grass` 按名字给 glTF 场景中的网格加上草地; 在 Inspector 中修改参数会重新生成.
+
+use bevy::core_pipeline::core_3d::Opaque3d;
+use bevy::core_pipeline::tonemapping::Tonemapping;
+use bevy::ecs::query::QueryItem;
+use bevy::ecs::system::lifetimeless::{Read, SRes};
+use bevy::ecs::system::SystemParamItem;
+use bevy::pbr::{LightEntity, MeshPipeline, MeshPipelineKey, MeshUniform, PrepassPipeline, RenderMaterials, SetMeshBindGroup, SetMeshViewBindGroup, SetPrepassViewBindGroup, Shadow, ViewLightEntities};
+use bevy::prelude::*;
+use bevy::render::extract_component::{ExtractComponent, ExtractComponentPlugin};
+use bevy::render::mesh::{GpuBufferInfo, Indices, MeshVertexBufferLayout, VertexAttributeValues};
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_phase::{AddRenderCommand, DrawFunctions, PhaseItem, RenderCommand, RenderCommandResult, RenderPhase, SetItemPipeline, TrackedRenderPass};
+use bevy::render::render_resource::*;
+use bevy::render::renderer::RenderDevice;
+use bevy::render::view::{ExtractedView, NoFrustumCulling, VisibleEntities};
+use bevy::render::{Render, RenderApp, RenderSet};
+use bevy::utils::HashMap;
+use bytemuck::{Pod, Zeroable};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use crate::CustomMaterial;
+use crate::bend::BendBuffer;
+
+pub struct GrassPlugin;
+
+impl Plugin for GrassPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<GrassField>()
+            .add_plugins((
+                ExtractComponentPlugin::<GrassInstances>::default(),
+                ExtractComponentPlugin::<GrassMaterial>::default(),
+            ))
+            .add_systems(Update, (despawn_orphans, generate_grass).chain());
+
+        app.sub_app_mut(RenderApp)
+            .add_render_command::<Opaque3d, DrawGrass>()
+            .add_render_command::<Shadow, DrawGrassShadow>()
+            .init_resource::<InstanceBuffers>()
+            .init_resource::<SpecializedMeshPipelines<GrassPipeline>>()
+            .init_resource::<SpecializedMeshPipelines<GrassShadowPipeline>>()
+            .add_systems(
+                Render,
+                (
+                    prepare_instance_buffers.in_set(RenderSet::Prepare),
+                    (queue_grass, queue_grass_shadows).in_set(RenderSet::Queue),
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        app.sub_app_mut(RenderApp)
+            .init_resource::<GrassPipeline>()
+            .init_resource::<GrassShadowPipeline>();
+    }
+}
+
+/// 一块草地最多的草叶数
+const MAX_BLADES: usize = 100_000;
+/// 法线 y 分量小于此值的三角形(侧面、底面)不长草
+const MIN_UP: f32 = 0.5;
+/// 草叶宽度(米)
+const BLADE_WIDTH: f32 = 0.06;
+
+#[derive(Component, Reflect, Serialize, Deserialize, Clone, Debug)]
+#[reflect(Component)]
+pub struct GrassField {
+    /// 每平方米的草叶数
+    pub density: f32,
+    /// 草叶高度(米)
+    pub height: f32,
+    /// 高度随机变化的比例, 0..1
+    pub height_variation: f32,
+    /// 相同的种子生成相同的草地
+    pub seed: u64,
+    /// 根部颜色
+    pub color: Color,
+    /// 叶尖颜色
+    pub tip_color: Color,
+}
+
+impl Default for GrassField {
+    fn default() -> Self {
+        Self {
+            density: 20.,
+            height: 0.4,
+            height_variation: 0.3,
+            seed: 0,
+            color: Color::rgb(0.15, 0.4, 0.1),
+            tip_color: Color::rgb(0.5, 0.8, 0.3),
+        }
+    }
+}
+
+/// 草地生成的实例化绘制实体, 记录它长在哪个网格上
+#[derive(Component)]
+struct GrassBlades {
+    surface: Entity,
+}
+
+/// 每根草叶的实例数据, 对应着色器中的 location 3 和 4
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct GrassInstance {
+    /// 根部的世界坐标和高度
+    position_height: [f32; 4],
+    /// 朝向 (cos, sin), 明暗, 摇摆相位
+    rotation_shade_phase: [f32; 4],
+}
+
+// 只有 f32 字段, 没有填充字节. 不用派生宏: 它生成的检查代码在 dead_code 下报错
+unsafe impl Zeroable for GrassInstance {}
+unsafe impl Pod for GrassInstance {}
+
+#[derive(Component, Clone, Deref)]
+struct GrassInstances(Vec<GrassInstance>);
+
+/// 渲染世界中的草地; 草叶数据只在生成或修改后的那一帧复制过来
+#[derive(Component)]
+struct ExtractedGrass {
+    changed: Option<Vec<GrassInstance>>,
+}
+
+impl ExtractComponent for GrassInstances {
+    type Query = Ref<'static, GrassInstances>;
+    type Filter = ();
+    type Out = ExtractedGrass;
+
+    fn extract_component(item: QueryItem<'_, Self::Query>) -> Option<ExtractedGrass> {
+        Some(ExtractedGrass {
+            changed: item.is_changed().then(|| item.0.clone()),
+        })
+    }
+}
+
+/// 草地共用的材质; 不直接使用 `Handle<CustomMaterial>`, 以免再被材质管线逐个绘制一遍
+#[derive(Component, Clone)]
+struct GrassMaterial(Handle<CustomMaterial>);
+
+impl ExtractComponent for GrassMaterial {
+    type Query = &'static GrassMaterial;
+    type Filter = ();
+    type Out = Self;
+
+    fn extract_component(item: QueryItem<'_, Self::Query>) -> Option<Self> {
+        Some(item.clone())
+    }
+}
+
+/// 一根高 1 米的草叶, 底宽 `BLADE_WIDTH`, 向上收成尖; 实例数据再按高度缩放
+fn blade_mesh() -> Mesh {
+    const SEGMENTS: usize = 4;
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    for i in 0..=SEGMENTS {
+        let y = i as f32 / SEGMENTS as f32;
+        let half_width = BLADE_WIDTH / 2. * (1. - y);
+        for x in [-half_width, half_width] {
+            positions.push([x, y, 0.]);
+            normals.push([0., 0., 1.]);
+            uvs.push([if x < 0. { 0. } else { 1. }, 1. - y]);
+        }
+    }
+    let mut indices = Vec::new();
+    for i in 0..SEGMENTS as u32 {
+        let base = i * 2;
+        indices.extend_from_slice(&[base, base + 1, base + 2, base + 1, base + 3, base + 2]);
+    }
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+/// 网格朝上的三角形, 世界坐标
+fn upward_triangles(mesh: &Mesh, transform: &GlobalTransform) -> Vec<[Vec3; 3]> {
+    let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+        return Vec::new();
+    };
+    let positions: Vec<Vec3> = positions.iter().map(|position| transform.transform_point(Vec3::from(*position))).collect();
+    let indices: Vec<usize> = match mesh.indices() {
+        Some(indices) => indices.iter().collect(),
+        None => (0..positions.len()).collect(),
+    };
+    indices
+        .chunks_exact(3)
+        .map(|triangle| [positions[triangle[0]], positions[triangle[1]], positions[triangle[2]]])
+        .filter(|[a, b, c]| (*b - *a).cross(*c - *a).normalize_or_zero().y >= MIN_UP)
+        .collect()
+}
+
+/// 按面积在三角形上均匀撒点
+fn scatter(field: &GrassField, triangles: &[[Vec3; 3]]) -> Vec<GrassInstance> {
+    let mut total = 0.;
+    let cumulative: Vec<f32> = triangles
+        .iter()
+        .map(|[a, b, c]| {
+            total += (*b - *a).cross(*c - *a).length() / 2.;
+            total
+        })
+        .collect();
+    let count = ((total * field.density) as usize).min(MAX_BLADES);
+    if triangles.is_empty() || count == 0 {
+        return Vec::new();
+    }
+
+    let mut rng = StdRng::seed_from_u64(field.seed);
+    (0..count)
+        .map(|_| {
+            let target = rng.gen_range(0.0..total);
+            let index = cumulative.partition_point(|area| *area < target).min(triangles.len() - 1);
+            let [a, b, c] = triangles[index];
+            // 均匀分布的重心坐标
+            let (r1, r2): (f32, f32) = (rng.gen(), rng.gen());
+            let sqrt_r1 = r1.sqrt();
+            let position = a * (1. - sqrt_r1) + b * (sqrt_r1 * (1. - r2)) + c * (sqrt_r1 * r2);
+            let height = field.height * (1. + field.height_variation * rng.gen_range(-1.0..=1.0));
+            let angle: f32 = rng.gen_range(0.0..std::f32::consts::TAU);
+            GrassInstance {
+                position_height: [position.x, position.y, position.z, height],
+                rotation_shade_phase: [angle.cos(), angle.sin(), rng.gen_range(0.7..=1.0), rng.gen_range(0.0..std::f32::consts::TAU)],
+            }
+        })
+        .collect()
+}
+
+/// 网格被销毁(比如换场地)后, 长在上面的草一起销毁
+fn despawn_orphans(mut commands: Commands, surfaces: Query<(), With<GrassField>>, blades: Query<(Entity, &GrassBlades)>) {
+    for (entity, blades) in blades.iter() {
+        if !surfaces.contains(blades.surface) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// 网格还没加载完成, 等加载后再生成草
+#[derive(Component)]
+struct PendingGrass;
+
+#[allow(clippy::type_complexity)]
+fn generate_grass(
+    mut commands: Commands,
+    mut blade: Local<Option<Handle<Mesh>>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<CustomMaterial>>,
+    bend_buffer: Res<BendBuffer>,
+    fields: Query<(Entity, &GrassField, &Handle<Mesh>, &GlobalTransform), Or<(Changed<GrassField>, With<PendingGrass>)>>,
+    blades: Query<(Entity, &GrassBlades)>,
+) {
+    for (surface, field, mesh, transform) in fields.iter() {
+        for (entity, blades) in blades.iter() {
+            if blades.surface == surface {
+                commands.entity(entity).despawn();
+            }
+        }
+        let Some(mesh) = meshes.get(mesh) else {
+            commands.entity(surface).insert(PendingGrass);
+            continue;
+        };
+        commands.entity(surface).remove::<PendingGrass>();
+        let instances = scatter(field, &upward_triangles(mesh, transform));
+        if instances.is_empty() {
+            continue;
+        }
+
+        let blade = blade.get_or_insert_with(|| meshes.add(blade_mesh())).clone();
+        commands.spawn((
+            blade,
+            SpatialBundle::default(),
+            GrassInstances(instances),
+            GrassMaterial(materials.add(CustomMaterial {
+                color: field.color,
+                tip_color: field.tip_color,
+                height_range: Vec2::new(0., 1.),
+                ..CustomMaterial::new(&bend_buffer)
+            })),
+            GrassBlades { surface },
+            // 草叶网格的包围盒只有一根草大小, 不能用来剔除整块草地
+            NoFrustumCulling,
+            Name::new("Grass"),
+        ));
+    }
+}
+
+struct InstanceBuffer {
+    buffer: Buffer,
+    length: usize,
+}
+
+/// 每块草地的实例缓冲, 跨帧保留; 渲染世界的实体每帧都会清空, 所以按实体存在资源里
+#[derive(Resource, Default, Deref, DerefMut)]
+struct InstanceBuffers(HashMap<Entity, InstanceBuffer>);
+
+fn prepare_instance_buffers(
+    mut buffers: ResMut<InstanceBuffers>,
+    query: Query<(Entity, &ExtractedGrass)>,
+    render_device: Res<RenderDevice>,
+) {
+    for (entity, grass) in query.iter() {
+        let Some(instances) = &grass.changed else {
+            continue;
+        };
+        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("grass instance buffer"),
+            contents: bytemuck::cast_slice(instances.as_slice()),
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        });
+        buffers.insert(entity, InstanceBuffer {
+            buffer,
+            length: instances.len(),
+        });
+    }
+    // 已销毁的草地不再被提取, 释放它们的缓冲
+    buffers.retain(|entity, _| query.contains(*entity));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn queue_grass(
+    draw_functions: Res<DrawFunctions<Opaque3d>>,
+    grass_pipeline: Res<GrassPipeline>,
+    msaa: Res<Msaa>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<GrassPipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    meshes: Res<RenderAssets<Mesh>>,
+    grass: Query<(&MeshUniform, &Handle<Mesh>), With<ExtractedGrass>>,
+    mut views: Query<(&ExtractedView, &VisibleEntities, Option<&Tonemapping>, &mut RenderPhase<Opaque3d>)>,
+) {
+    let draw_grass = draw_functions.read().id::<DrawGrass>();
+    let msaa_key = MeshPipelineKey::from_msaa_samples(msaa.samples());
+
+    for (view, visible_entities, tonemapping, mut opaque_phase) in views.iter_mut() {
+        let mut view_key = msaa_key | MeshPipelineKey::from_hdr(view.hdr);
+        // 草叶有光照, 与其他材质一样在着色器中做色调映射
+        if !view.hdr {
+            if let Some(tonemapping) = tonemapping {
+                view_key |= MeshPipelineKey::TONEMAP_IN_SHADER | tonemapping_key(tonemapping);
+            }
+        }
+        let rangefinder = view.rangefinder3d();
+        for entity in &visible_entities.entities {
+            let Ok((mesh_uniform, mesh_handle)) = grass.get(*entity) else {
+                continue;
+            };
+            let Some(mesh) = meshes.get(mesh_handle) else {
+                continue;
+            };
+            let key = view_key | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology);
+            let Ok(pipeline) = pipelines.specialize(&pipeline_cache, &grass_pipeline, key, &mesh.layout) else {
+                continue;
+            };
+            opaque_phase.add(Opaque3d {
+                entity: *entity,
+                pipeline,
+                draw_function: draw_grass,
+                distance: rangefinder.distance(&mesh_uniform.transform),
+            });
+        }
+    }
+}
+
+fn tonemapping_key(tonemapping: &Tonemapping) -> MeshPipelineKey {
+    match tonemapping {
+        Tonemapping::None => MeshPipelineKey::TONEMAP_METHOD_NONE,
+        Tonemapping::Reinhard => MeshPipelineKey::TONEMAP_METHOD_REINHARD,
+        Tonemapping::ReinhardLuminance => MeshPipelineKey::TONEMAP_METHOD_REINHARD_LUMINANCE,
+        Tonemapping::AcesFitted => MeshPipelineKey::TONEMAP_METHOD_ACES_FITTED,
+        Tonemapping::AgX => MeshPipelineKey::TONEMAP_METHOD_AGX,
+        Tonemapping::SomewhatBoringDisplayTransform => MeshPipelineKey::TONEMAP_METHOD_SOMEWHAT_BORING_DISPLAY_TRANSFORM,
+        Tonemapping::TonyMcMapface => MeshPipelineKey::TONEMAP_METHOD_TONY_MC_MAPFACE,
+        Tonemapping::BlenderFilmic => MeshPipelineKey::TONEMAP_METHOD_BLENDER_FILMIC,
+    }
+}
+
+/// 草地加入每个平行光的阴影; 点光源和聚光灯的阴影不包括草
+#[allow(clippy::too_many_arguments)]
+fn queue_grass_shadows(
+    draw_functions: Res<DrawFunctions<Shadow>>,
+    shadow_pipeline: Res<GrassShadowPipeline>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<GrassShadowPipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    meshes: Res<RenderAssets<Mesh>>,
+    grass: Query<(Entity, &Handle<Mesh>), With<ExtractedGrass>>,
+    view_lights: Query<&ViewLightEntities>,
+    mut light_phases: Query<(&LightEntity, &mut RenderPhase<Shadow>)>,
+) {
+    let draw_shadow = draw_functions.read().id::<DrawGrassShadow>();
+
+    for view_lights in view_lights.iter() {
+        for light_entity in view_lights.lights.iter().copied() {
+            let Ok((light, mut shadow_phase)) = light_phases.get_mut(light_entity) else {
+                continue;
+            };
+            if !matches!(light, LightEntity::Directional { .. }) {
+                continue;
+            }
+            for (entity, mesh_handle) in grass.iter() {
+                let Some(mesh) = meshes.get(mesh_handle) else {
+                    continue;
+                };
+                let key = MeshPipelineKey::from_primitive_topology(mesh.primitive_topology)
+                    | MeshPipelineKey::DEPTH_PREPASS
+                    | MeshPipelineKey::DEPTH_CLAMP_ORTHO;
+                let Ok(pipeline) = pipelines.specialize(&pipeline_cache, &shadow_pipeline, key, &mesh.layout) else {
+                    continue;
+                };
+                shadow_phase.add(Shadow {
+                    draw_function: draw_shadow,
+                    pipeline,
+                    entity,
+                    distance: 0.,
+                });
+            }
+        }
+    }
+}
+
+#[derive(Resource)]
+struct GrassPipeline {
+    shader: Handle<Shader>,
+    mesh_pipeline: MeshPipeline,
+    material_layout: BindGroupLayout,
+}
+
+impl FromWorld for GrassPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        let shader = asset_server.load("shaders/grass.wgsl");
+        let material_layout = CustomMaterial::bind_group_layout(world.resource::<RenderDevice>());
+        let mesh_pipeline = world.resource::<MeshPipeline>().clone();
+        Self {
+            shader,
+            mesh_pipeline,
+            material_layout,
+        }
+    }
+}
+
+/// 草叶实例数据: 根部坐标和高度 location 3, 朝向、明暗和相位 location 4
+fn instance_layout() -> VertexBufferLayout {
+    VertexBufferLayout {
+        array_stride: std::mem::size_of::<GrassInstance>() as u64,
+        step_mode: VertexStepMode::Instance,
+        attributes: vec![
+            VertexAttribute {
+                format: VertexFormat::Float32x4,
+                offset: 0,
+                shader_location: 3,
+            },
+            VertexAttribute {
+                format: VertexFormat::Float32x4,
+                offset: VertexFormat::Float32x4.size(),
+                shader_location: 4,
+            },
+        ],
+    }
+}
+
+impl SpecializedMeshPipeline for GrassPipeline {
+    type Key = MeshPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayout,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut descriptor = self.mesh_pipeline.specialize(key, layout)?;
+        descriptor.label = Some("grass_pipeline".into());
+        descriptor.vertex.shader = self.shader.clone();
+        // 草叶网格只有位置、法线和 UV, 占用 location 0..=2
+        descriptor.vertex.buffers.push(instance_layout());
+        descriptor.fragment.as_mut().unwrap().shader = self.shader.clone();
+        // 草叶两面都可见
+        descriptor.primitive.cull_mode = None;
+        // 视图 0, 材质 1, 网格 2, 与 `CustomMaterial` 的着色器一致
+        descriptor.layout.insert(1, self.material_layout.clone());
+        Ok(descriptor)
+    }
+}
+
+/// 阴影预渲染: 只写深度, 视图绑定组使用预渲染的布局
+#[derive(Resource)]
+struct GrassShadowPipeline {
+    shader: Handle<Shader>,
+    view_layout: BindGroupLayout,
+    material_layout: BindGroupLayout,
+    mesh_layout: BindGroupLayout,
+}
+
+impl FromWorld for GrassShadowPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let shader = world.resource::<AssetServer>().load("shaders/grass_shadow.wgsl");
+        let prepass_pipeline = world.resource::<PrepassPipeline<CustomMaterial>>();
+        Self {
+            shader,
+            view_layout: prepass_pipeline.view_layout_no_motion_vectors.clone(),
+            material_layout: prepass_pipeline.material_layout.clone(),
+            mesh_layout: prepass_pipeline.mesh_layouts.model_only.clone(),
+        }
+    }
+}
+
+impl SpecializedMeshPipeline for GrassShadowPipeline {
+    type Key = MeshPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayout,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut shader_defs: Vec<ShaderDefVal> = Vec::new();
+        // 平行光阴影需要在片元着色器中写回被压到近平面的深度
+        let fragment = if key.contains(MeshPipelineKey::DEPTH_CLAMP_ORTHO) {
+            shader_defs.push("DEPTH_CLAMP_ORTHO".into());
+            Some(FragmentState {
+                shader: self.shader.clone(),
+                shader_defs: shader_defs.clone(),
+                entry_point: "fragment".into(),
+                targets: vec![],
+            })
+        } else {
+            None
+        };
+        let vertex_layout = layout.get_layout(&[Mesh::ATTRIBUTE_POSITION.at_shader_location(0)])?;
+        Ok(RenderPipelineDescriptor {
+            label: Some("grass_shadow_pipeline".into()),
+            layout: vec![self.view_layout.clone(), self.material_layout.clone(), self.mesh_layout.clone()],
+            push_constant_ranges: vec![],
+            vertex: VertexState {
+                shader: self.shader.clone(),
+                shader_defs,
+                entry_point: "vertex".into(),
+                buffers: vec![vertex_layout, instance_layout()],
+            },
+            fragment,
+            primitive: PrimitiveState {
+                topology: key.primitive_topology(),
+                cull_mode: None,
+                ..default()
+            },
+            depth_stencil: Some(DepthStencilState {
+                format: TextureFormat::Depth32Float,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::GreaterEqual,
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            multisample: MultisampleState::default(),
+        })
+    }
+}
+
+type DrawGrass = (
+    SetItemPipeline,
+    SetMeshViewBindGroup<0>,
+    SetGrassMaterialBindGroup<1>,
+    SetMeshBindGroup<2>,
+    DrawGrassInstanced,
+);
+
+type DrawGrassShadow = (
+    SetItemPipeline,
+    SetPrepassViewBindGroup<0>,
+    SetGrassMaterialBindGroup<1>,
+    SetMeshBindGroup<2>,
+    DrawGrassInstanced,
+);
+
+struct SetGrassMaterialBindGroup<const I: usize>;
+
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetGrassMaterialBindGroup<I> {
+    type Param = SRes<RenderMaterials<CustomMaterial>>;
+    type ViewWorldQuery = ();
+    type ItemWorldQuery = Read<GrassMaterial>;
+
+    #[inline]
+    fn render<'w>(
+        _item: &P,
+        _view: (),
+        material: &'w GrassMaterial,
+        materials: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(material) = materials.into_inner().get(&material.0) else {
+            return RenderCommandResult::Failure;
+        };
+        pass.set_bind_group(I, &material.bind_group, &[]);
+        RenderCommandResult::Success
+    }
+}
+
+struct DrawGrassInstanced;
+
+impl<P: PhaseItem> RenderCommand<P> for DrawGrassInstanced {
+    type Param = (SRes<RenderAssets<Mesh>>, SRes<InstanceBuffers>);
+    type ViewWorldQuery = ();
+    type ItemWorldQuery = Read<Handle<Mesh>>;
+
+    #[inline]
+    fn render<'w>(
+        item: &P,
+        _view: (),
+        mesh_handle: &'w Handle<Mesh>,
+        (meshes, buffers): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(gpu_mesh) = meshes.into_inner().get(mesh_handle) else {
+            return RenderCommandResult::Failure;
+        };
+        let Some(instance_buffer) = buffers.into_inner().get(&item.entity()) else {
+            return RenderCommandResult::Failure;
+        };
+        pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+        pass.set_vertex_buffer(1, instance_buffer.buffer.slice(..));
+
+        match &gpu_mesh.buffer_info {
+            GpuBufferInfo::Indexed { buffer, index_format, count } => {
+                pass.set_index_buffer(buffer.slice(..), 0, *index_format);
+                pass.draw_indexed(0..*count, 0, 0..instance_buffer.length as u32);
+            }
+            GpuBufferInfo::NonIndexed => {
+                pass.draw(0..gpu_mesh.vertex_count, 0..instance_buffer.length as u32);
+            }
+        }
+        RenderCommandResult::Success
+    }
+}
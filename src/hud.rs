@@ -0,0 +1,511 @@
+//! 对战 HUD: 生命条、气槽、角色名、回合胜场、倒计时、连击数和伤害数字.
+//! HUD 根节点按 `GameViewport` 定位, Inspector 打开时只覆盖游戏画面所在的区域.
+
+use std::collections::HashMap;
+use bevy::prelude::*;
+use crate::{CharacterName, CharacterState, CombatEvent, FlatCamera, GameState, GameViewport, Health, MainCamera, Meter, MyAssets, UID};
+use crate::billboard::{CombatPlane, FightView};
+use crate::round::{Round, ROUND_END_SECONDS, ROUNDS_TO_WIN};
+use crate::meter::SuperFreeze;
+use crate::select::PLAYER_COLORS;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameViewport>()
+            .init_resource::<Combos>()
+            .add_systems(OnEnter(GameState::Playing), setup)
+            .add_systems(
+                Update,
+                (
+                    sync_root,
+                    hide_flat_camera_ui,
+                    update_names,
+                    update_bars,
+                    update_pips,
+                    update_timer,
+                    (count_combos, update_combo_texts).chain(),
+                    spawn_damage_popups,
+                    animate_popups,
+                    show_round_banner,
+                    dim_on_freeze,
+                )
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), cleanup);
+    }
+}
+
+/// 连击数和伤害数字的显示时长(秒)
+const POPUP_SECONDS: f32 = 1.2;
+/// 回合结果的显示时长(秒)
+const BANNER_SECONDS: f32 = ROUND_END_SECONDS;
+const HEALTH_COLOR: Color = Color::rgb(0.95, 0.8, 0.2);
+const METER_COLOR: Color = Color::rgb(0.3, 0.8, 1.);
+const BAR_BACKGROUND: Color = Color::rgba(0., 0., 0., 0.6);
+
+#[derive(Component)]
+struct HudRoot;
+
+#[derive(Component)]
+struct HealthBar(UID);
+
+#[derive(Component)]
+struct MeterBar(UID);
+
+#[derive(Component)]
+struct FighterName(UID);
+
+/// 回合胜场标记, 第 `index` 个胜场
+#[derive(Component)]
+struct RoundPip {
+    uid: UID,
+    index: u32,
+}
+
+#[derive(Component)]
+struct RoundTimerText;
+
+#[derive(Component)]
+struct RoundNumberText;
+
+/// 攻击方一侧的连击数
+#[derive(Component)]
+struct ComboText(UID);
+
+/// 回合结束时的提示
+#[derive(Component)]
+struct RoundBanner(Timer);
+
+/// 超必杀定格时压暗画面
+#[derive(Component)]
+struct FreezeDim;
+
+/// 受击角色头顶飘出的伤害数字
+#[derive(Component)]
+struct DamagePopup(Timer);
+
+/// 连击: 对方仍处于受击状态时连续命中的次数和总伤害
+#[derive(Default, Clone, Copy)]
+struct Combo {
+    hits: u32,
+    damage: f32,
+    /// 对方已经脱离受击状态, 连击中断
+    ended: bool,
+    /// 连击中断后已经显示的时间
+    linger: f32,
+}
+
+/// 攻击方 -> 连击
+#[derive(Resource, Deref, DerefMut, Default)]
+struct Combos(HashMap<UID, Combo>);
+
+fn setup(mut commands: Commands, my_assets: Res<MyAssets>) {
+    let text_style = |font_size: f32, color: Color| TextStyle {
+        font: my_assets.font.clone(),
+        font_size,
+        color,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::SpaceBetween,
+                    padding: UiRect::all(Val::Px(16.)),
+                    ..default()
+                },
+                ..default()
+            },
+            HudRoot,
+            Name::new("HUD"),
+        ))
+        .with_children(|root| {
+            root.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        width: Val::Percent(100.),
+                        height: Val::Percent(100.),
+                        ..default()
+                    },
+                    background_color: Color::NONE.into(),
+                    ..default()
+                },
+                FreezeDim,
+            ));
+
+            root.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    justify_content: JustifyContent::SpaceBetween,
+                    align_items: AlignItems::FlexStart,
+                    ..default()
+                },
+                ..default()
+            }).with_children(|top| {
+                spawn_fighter_panel(top, UID(1), &text_style);
+
+                top.spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    ..default()
+                }).with_children(|center| {
+                    center.spawn((TextBundle::from_section("", text_style(48., Color::WHITE)), RoundTimerText));
+                    center.spawn((TextBundle::from_section("", text_style(20., Color::WHITE)), RoundNumberText));
+                });
+
+                spawn_fighter_panel(top, UID(2), &text_style);
+            });
+
+            root.spawn((
+                TextBundle::from_section("", text_style(72., Color::WHITE)).with_style(Style {
+                    align_self: AlignSelf::Center,
+                    ..default()
+                }),
+                RoundBanner(Timer::from_seconds(BANNER_SECONDS, TimerMode::Once)),
+            ));
+
+            // 连击数显示在攻击方一侧
+            root.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    justify_content: JustifyContent::SpaceBetween,
+                    ..default()
+                },
+                ..default()
+            }).with_children(|bottom| {
+                for uid in [UID(1), UID(2)] {
+                    bottom.spawn((
+                        TextBundle::from_section("", text_style(36., player_color(uid))),
+                        ComboText(uid),
+                    ));
+                }
+            });
+        });
+}
+
+/// 角色名 + 生命条 + 气槽 + 胜场, 2P 的面板左右镜像
+fn spawn_fighter_panel(parent: &mut ChildBuilder, uid: UID, text_style: &impl Fn(f32, Color) -> TextStyle) {
+    let mirrored = uid.0.is_multiple_of(2);
+    let align = if mirrored { AlignItems::FlexEnd } else { AlignItems::FlexStart };
+    let justify = if mirrored { JustifyContent::FlexEnd } else { JustifyContent::FlexStart };
+
+    parent.spawn(NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Column,
+            align_items: align,
+            row_gap: Val::Px(4.),
+            ..default()
+        },
+        ..default()
+    }).with_children(|panel| {
+        panel.spawn((TextBundle::from_section("", text_style(24., player_color(uid))), FighterName(uid)));
+
+        spawn_bar(panel, Vec2::new(400., 24.), HEALTH_COLOR, justify, HealthBar(uid));
+        spawn_bar(panel, Vec2::new(260., 10.), METER_COLOR, justify, MeterBar(uid));
+
+        panel.spawn(NodeBundle {
+            style: Style {
+                column_gap: Val::Px(6.),
+                justify_content: justify,
+                ..default()
+            },
+            ..default()
+        }).with_children(|pips| {
+            for index in 0..ROUNDS_TO_WIN {
+                pips.spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Px(14.),
+                            height: Val::Px(14.),
+                            ..default()
+                        },
+                        background_color: BAR_BACKGROUND.into(),
+                        ..default()
+                    },
+                    RoundPip { uid, index },
+                ));
+            }
+        });
+    });
+}
+
+/// 底框 + 按比例缩放宽度的填充条, `marker` 插入到填充条上; 2P 的条从右侧开始
+fn spawn_bar(parent: &mut ChildBuilder, size: Vec2, color: Color, justify: JustifyContent, marker: impl Component) {
+    parent.spawn(NodeBundle {
+        style: Style {
+            width: Val::Px(size.x),
+            height: Val::Px(size.y),
+            justify_content: justify,
+            padding: UiRect::all(Val::Px(2.)),
+            ..default()
+        },
+        background_color: BAR_BACKGROUND.into(),
+        ..default()
+    }).with_children(|background| {
+        background.spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    ..default()
+                },
+                background_color: color.into(),
+                ..default()
+            },
+            marker,
+        ));
+    });
+}
+
+fn player_color(uid: UID) -> Color {
+    PLAYER_COLORS[(uid.0 as usize + PLAYER_COLORS.len() - 1) % PLAYER_COLORS.len()]
+}
+
+/// HUD 跟随游戏画面区域; UI 坐标以相机视口左上角为原点, 所以只需要同步尺寸
+fn sync_root(viewport: Res<GameViewport>, mut roots: Query<(&mut Style, Ref<HudRoot>)>) {
+    let (width, height) = match **viewport {
+        Some(rect) => (Val::Px(rect.width()), Val::Px(rect.height())),
+        None => (Val::Percent(100.), Val::Percent(100.)),
+    };
+    for (mut style, root) in roots.iter_mut() {
+        if !viewport.is_changed() && !root.is_added() {
+            continue;
+        }
+        style.width = width;
+        style.height = height;
+    }
+}
+
+/// HUD 只画在 3D 主相机上, 避免叠加的 2D 相机再画一遍
+fn hide_flat_camera_ui(mut commands: Commands, cameras: Query<Entity, (With<FlatCamera>, Without<UiCameraConfig>)>) {
+    for entity in cameras.iter() {
+        commands.entity(entity).insert(UiCameraConfig { show_ui: false });
+    }
+}
+
+fn update_names(
+    fighters: Query<(&UID, &CharacterName), Changed<CharacterName>>,
+    mut names: Query<(&FighterName, &mut Text)>,
+) {
+    for (uid, character_name) in fighters.iter() {
+        for (name, mut text) in names.iter_mut() {
+            if name.0 == *uid {
+                text.sections[0].value = character_name.to_uppercase();
+            }
+        }
+    }
+}
+
+fn update_bars(
+    fighters: Query<(&UID, &Health, &Meter)>,
+    mut health_bars: Query<(&HealthBar, &mut Style), Without<MeterBar>>,
+    mut meter_bars: Query<(&MeterBar, &mut Style), Without<HealthBar>>,
+) {
+    for (uid, health, meter) in fighters.iter() {
+        for (bar, mut style) in health_bars.iter_mut() {
+            if bar.0 == *uid {
+                style.width = Val::Percent(health.fraction() * 100.);
+            }
+        }
+        for (bar, mut style) in meter_bars.iter_mut() {
+            if bar.0 == *uid {
+                style.width = Val::Percent(meter.fraction() * 100.);
+            }
+        }
+    }
+}
+
+fn update_pips(round: Res<Round>, mut pips: Query<(&RoundPip, &mut BackgroundColor)>) {
+    if !round.is_changed() {
+        return;
+    }
+    for (pip, mut background) in pips.iter_mut() {
+        *background = if pip.index < round.wins(pip.uid) {
+            player_color(pip.uid)
+        } else {
+            BAR_BACKGROUND
+        }.into();
+    }
+}
+
+fn update_timer(
+    round: Res<Round>,
+    mut timers: Query<&mut Text, (With<RoundTimerText>, Without<RoundNumberText>)>,
+    mut numbers: Query<&mut Text, (With<RoundNumberText>, Without<RoundTimerText>)>,
+) {
+    for mut text in timers.iter_mut() {
+        text.sections[0].value = format!("{:02}", round.seconds_left());
+    }
+    for mut text in numbers.iter_mut() {
+        text.sections[0].value = format!("ROUND {}", round.number);
+    }
+}
+
+fn count_combos(
+    time: Res<Time>,
+    mut events: EventReader<CombatEvent>,
+    mut combos: ResMut<Combos>,
+    fighters: Query<(&UID, &CharacterState)>,
+) {
+    for event in events.iter() {
+        if let CombatEvent::Damage { uid, attacker, damage, .. } = event {
+            let combo = combos.entry(*attacker).or_default();
+            if combo.ended {
+                *combo = Combo::default();
+            }
+            combo.hits += 1;
+            combo.damage += damage;
+            debug!("combo {:?} -> {:?}: {}", attacker, uid, combo.hits);
+        }
+    }
+
+    // 对方离开受击状态时连击结束, 之后再显示一会儿
+    for (attacker, combo) in combos.iter_mut() {
+        let victim_stunned = fighters
+            .iter()
+            .any(|(uid, state)| uid != attacker && matches!(state, CharacterState::Hit { .. }));
+        combo.ended |= !victim_stunned;
+        if combo.ended {
+            combo.linger += time.delta_seconds();
+        }
+    }
+    combos.retain(|_, combo| combo.linger < POPUP_SECONDS);
+}
+
+fn update_combo_texts(combos: Res<Combos>, mut texts: Query<(&ComboText, &mut Text)>) {
+    for (combo_text, mut text) in texts.iter_mut() {
+        text.sections[0].value = match combos.get(&combo_text.0) {
+            Some(combo) if combo.hits >= 2 => format!("{} HITS\n{:.0} DMG", combo.hits, combo.damage),
+            _ => String::new(),
+        };
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn spawn_damage_popups(
+    mut commands: Commands,
+    mut events: EventReader<CombatEvent>,
+    my_assets: Res<MyAssets>,
+    view: Res<FightView>,
+    plane: Res<CombatPlane>,
+    viewport: Res<GameViewport>,
+    roots: Query<Entity, With<HudRoot>>,
+    fighters: Query<(&UID, &GlobalTransform)>,
+    main_cameras: Query<(&Camera, &GlobalTransform), (With<MainCamera>, Without<FlatCamera>)>,
+    flat_cameras: Query<(&Camera, &GlobalTransform), (With<FlatCamera>, Without<MainCamera>)>,
+) {
+    let Ok(root) = roots.get_single() else {
+        return;
+    };
+    for event in events.iter() {
+        let CombatEvent::Damage { uid, damage, .. } = event else {
+            continue;
+        };
+        let Some((_, fighter_transform)) = fighters.iter().find(|(fighter, _)| *fighter == uid) else {
+            continue;
+        };
+        // 受击角色头顶在 HUD 中的位置: HUD 以主相机视口为原点, 2D 相机覆盖整个窗口, 需要减去视口偏移
+        let head = fighter_transform.translation().truncate() + Vec2::new(0., 60.);
+        let position = match *view {
+            FightView::Billboard => main_cameras
+                .get_single()
+                .ok()
+                .and_then(|(camera, camera_transform)| camera.world_to_viewport(camera_transform, plane.to_world(head))),
+            FightView::Flat => flat_cameras
+                .get_single()
+                .ok()
+                .and_then(|(camera, camera_transform)| camera.world_to_viewport(camera_transform, head.extend(0.)))
+                .map(|position| position - viewport.map_or(Vec2::ZERO, |rect| rect.min)),
+        };
+        let Some(position) = position else {
+            continue;
+        };
+
+        let popup = commands.spawn((
+            TextBundle::from_section(format!("{:.0}", damage), TextStyle {
+                font: my_assets.font.clone(),
+                font_size: 28.,
+                color: Color::WHITE,
+            }).with_style(Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(position.x),
+                top: Val::Px(position.y),
+                ..default()
+            }),
+            DamagePopup(Timer::from_seconds(POPUP_SECONDS, TimerMode::Once)),
+        )).id();
+        commands.entity(root).add_child(popup);
+    }
+}
+
+/// 伤害数字向上飘并淡出
+fn animate_popups(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut popups: Query<(Entity, &mut DamagePopup, &mut Style, &mut Text)>,
+) {
+    for (entity, mut popup, mut style, mut text) in popups.iter_mut() {
+        popup.0.tick(time.delta());
+        if popup.0.finished() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        if let Val::Px(top) = style.top {
+            style.top = Val::Px(top - 40. * time.delta_seconds());
+        }
+        text.sections[0].style.color.set_a(popup.0.percent_left());
+    }
+}
+
+fn show_round_banner(
+    time: Res<Time>,
+    mut events: EventReader<CombatEvent>,
+    mut banners: Query<(&mut RoundBanner, &mut Text)>,
+) {
+    let Ok((mut banner, mut text)) = banners.get_single_mut() else {
+        return;
+    };
+    for event in events.iter() {
+        let CombatEvent::RoundOver { winner, timeout } = event else {
+            continue;
+        };
+        let title = if *timeout { "TIME" } else { "K.O." };
+        text.sections[0].value = match winner {
+            Some(winner) => format!("{}\n{}P WIN", title, winner.0),
+            None => format!("{}\nDRAW", title),
+        };
+        text.sections[0].style.color = winner.map_or(Color::WHITE, player_color);
+        banner.0.reset();
+    }
+
+    banner.0.tick(time.delta());
+    if banner.0.just_finished() {
+        text.sections[0].value.clear();
+    }
+}
+
+fn dim_on_freeze(freeze: Res<SuperFreeze>, mut dims: Query<&mut BackgroundColor, With<FreezeDim>>) {
+    if !freeze.is_changed() {
+        return;
+    }
+    let color = if freeze.active() { Color::rgba(0., 0., 0., 0.5) } else { Color::NONE };
+    for mut background in dims.iter_mut() {
+        background.0 = color;
+    }
+}
+
+fn cleanup(mut commands: Commands, query: Query<Entity, With<HudRoot>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,338 @@
+//! 关卡: glTF 场景(包括场地的道具)加载完成后, 按节点的 extras(Blender 中的自定义属性)生成玩法组件, 让 Blender 中搭的场景成为可玩的场地.
+//! - `collider`: "box" / "mesh" / "convex", 节点的网格投影到战斗平面上生成碰撞体
+//! - `spawn`: 玩家编号(1P = 1), 节点位置作为开场位置
+//! - `trigger`: 触发区名字, 网格投影生成感应区, 角色进出时发送 `TriggerEvent`
+//! - `anchor`: 锚点名字, 供特效等按名字查找位置
+//! - `light`: `{"color": [r, g, b], "intensity": 800, "range": 20}`, 在节点上生成点光源
+
+use bevy::gltf::GltfExtras;
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, VertexAttributeValues};
+use bevy::render::primitives::Aabb;
+use bevy::render::view::NoFrustumCulling;
+use bevy_rapier2d::prelude::*;
+use serde::Deserialize;
+use crate::{GameState, UID};
+use crate::billboard::CombatPlane;
+use crate::stage::{CurrentStage, StageEntity};
+use crate::tools::SceneEvent;
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TriggerEvent>()
+            .add_systems(Update, (
+                build_level,
+                detect_triggers,
+            ).run_if(in_state(GameState::Playing)));
+    }
+}
+
+/// 角色碰撞体中心到脚底的距离(像素), 开场位置标记放在地面上
+const SPAWN_LIFT: f32 = 30.;
+/// 场景加载后最多等待网格包围盒的时间(秒), 加载失败的网格永远不会有包围盒
+const BOUNDS_TIMEOUT: f32 = 5.;
+
+/// 节点 extras 中与玩法有关的属性, 其他属性(比如 `material`)忽略
+#[derive(Deserialize, Default, Debug)]
+struct NodeExtras {
+    #[serde(default)]
+    collider: Option<ColliderShape>,
+    #[serde(default)]
+    spawn: Option<u32>,
+    #[serde(default)]
+    trigger: Option<String>,
+    #[serde(default)]
+    anchor: Option<String>,
+    #[serde(default)]
+    light: Option<LightExtras>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+enum ColliderShape {
+    /// 包围盒
+    Box,
+    /// 三角网格, 适合凹的地形
+    Mesh,
+    /// 凸包
+    Convex,
+}
+
+#[derive(Deserialize, Debug)]
+struct LightExtras {
+    #[serde(default = "LightExtras::default_color")]
+    color: [f32; 3],
+    #[serde(default = "LightExtras::default_intensity")]
+    intensity: f32,
+    #[serde(default = "LightExtras::default_range")]
+    range: f32,
+}
+
+impl LightExtras {
+    fn default_color() -> [f32; 3] {
+        [1., 1., 1.]
+    }
+
+    fn default_intensity() -> f32 {
+        800.
+    }
+
+    fn default_range() -> f32 {
+        20.
+    }
+}
+
+/// 触发区, 角色进出时发送 `TriggerEvent`
+#[derive(Component, Debug)]
+pub struct Trigger {
+    pub name: String,
+}
+
+#[derive(Event, Debug)]
+pub struct TriggerEvent {
+    pub trigger: String,
+    pub fighter: Entity,
+    pub entered: bool,
+}
+
+/// 场景中的锚点, 位置取节点的 `GlobalTransform`
+#[derive(Component, Debug)]
+pub struct StageAnchor {
+    pub name: String,
+}
+
+/// 已经处理过 extras 的节点
+#[derive(Component)]
+struct LevelNode;
+
+/// 节点下所有网格投影到战斗平面上的几何
+#[derive(Default)]
+struct Projected {
+    min: Vec2,
+    max: Vec2,
+    vertices: Vec<Vec2>,
+    triangles: Vec<[u32; 3]>,
+}
+
+impl Projected {
+    fn collider(&self, shape: ColliderShape) -> Option<(Collider, Vec2)> {
+        match shape {
+            ColliderShape::Box => {
+                if self.min.cmpgt(self.max).any() {
+                    return None;
+                }
+                let half = (self.max - self.min) / 2.;
+                Some((Collider::cuboid(half.x, half.y), (self.min + self.max) / 2.))
+            }
+            ColliderShape::Convex => Collider::convex_hull(&self.vertices).map(|collider| (collider, Vec2::ZERO)),
+            ColliderShape::Mesh => {
+                (!self.triangles.is_empty()).then(|| (Collider::trimesh(self.vertices.clone(), self.triangles.clone()), Vec2::ZERO))
+            }
+        }
+    }
+}
+
+/// 关卡节点下参与投影的网格; 不做视锥剔除的网格(草地)不算
+type MeshEntities<'w, 's> = Query<'w, 's, (&'static GlobalTransform, &'static Handle<Mesh>, Option<&'static Aabb>), Without<NoFrustumCulling>>;
+
+#[allow(clippy::too_many_arguments)]
+fn build_level(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut pending: Local<Option<Timer>>,
+    mut scene_events: EventReader<SceneEvent>,
+    plane: Res<CombatPlane>,
+    mut stage: ResMut<CurrentStage>,
+    nodes: Query<(Entity, &GltfExtras, &GlobalTransform, Option<&Name>), Without<LevelNode>>,
+    children: Query<&Children>,
+    mesh_entities: MeshEntities,
+    meshes: Res<Assets<Mesh>>,
+    mut fighters: Query<(&UID, &mut Transform)>,
+) {
+    if scene_events.iter().any(|event| matches!(event, SceneEvent::Loaded { .. })) {
+        *pending = Some(Timer::from_seconds(BOUNDS_TIMEOUT, TimerMode::Once));
+    }
+    let Some(timer) = pending.as_mut() else {
+        return;
+    };
+    // 包围盒在场景生成后的 PostUpdate 中计算; 不做视锥剔除的网格(草地)和没有顶点坐标的网格没有包围盒
+    let waiting = mesh_entities
+        .iter()
+        .filter(|(_, handle, aabb)| {
+            aabb.is_none()
+                && meshes.get(handle).is_none_or(|mesh| {
+                    matches!(mesh.attribute(Mesh::ATTRIBUTE_POSITION), Some(VertexAttributeValues::Float32x3(_)))
+                })
+        })
+        .count();
+    if waiting > 0 {
+        if !timer.tick(time.delta()).just_finished() {
+            return;
+        }
+        warn!("building the level without the bounds of {waiting} meshes that are still loading or failed to load");
+    }
+    *pending = None;
+
+    for (entity, extras, transform, name) in nodes.iter() {
+        commands.entity(entity).insert(LevelNode);
+        let extras: NodeExtras = match serde_json::from_str(&extras.value) {
+            Ok(extras) => extras,
+            Err(err) => {
+                warn!("invalid glTF extras on {:?}: {err}", name);
+                continue;
+            }
+        };
+        let name = name.map_or_else(|| format!("{entity:?}"), |name| name.to_string());
+
+        if extras.collider.is_some() || extras.trigger.is_some() {
+            let projected = project(entity, &plane, &children, &mesh_entities, &meshes);
+            if let Some(shape) = extras.collider {
+                match projected.collider(shape) {
+                    Some((collider, center)) => {
+                        commands.spawn((
+                            collider,
+                            TransformBundle::from(Transform::from_translation(center.extend(0.))),
+                            Restitution {
+                                coefficient: stage.restitution.unwrap_or(0.),
+                                combine_rule: CoefficientCombineRule::Min,
+                            },
+                            Friction {
+                                coefficient: stage.friction.unwrap_or(2.),
+                                combine_rule: CoefficientCombineRule::Max,
+                            },
+                            StageEntity,
+                            Name::new(format!("Collider {name}")),
+                        ));
+                    }
+                    None => warn!("{name}: no geometry for a {shape:?} collider"),
+                }
+            }
+            if let Some(trigger) = extras.trigger {
+                match projected.collider(ColliderShape::Box) {
+                    Some((collider, center)) => {
+                        commands.spawn((
+                            collider,
+                            Sensor,
+                            ActiveEvents::COLLISION_EVENTS,
+                            TransformBundle::from(Transform::from_translation(center.extend(0.))),
+                            Trigger { name: trigger },
+                            StageEntity,
+                            Name::new(format!("Trigger {name}")),
+                        ));
+                    }
+                    None => warn!("{name}: no geometry for a trigger"),
+                }
+            }
+        }
+
+        if let Some(uid) = extras.spawn.filter(|uid| *uid > 0) {
+            let position = plane.from_world(transform.translation()) + Vec2::new(0., SPAWN_LIFT);
+            let index = uid as usize - 1;
+            while stage.spawns.len() <= index {
+                let next = stage.spawn_point(UID(stage.spawns.len() as u32 + 1)).truncate();
+                stage.spawns.push(next);
+            }
+            stage.spawns[index] = position;
+            // 场景晚于角色加载, 把已经生成的角色移到开场位置
+            for (fighter_uid, mut fighter_transform) in fighters.iter_mut() {
+                if fighter_uid.0 == uid {
+                    fighter_transform.translation = position.extend(fighter_transform.translation.z);
+                }
+            }
+        }
+
+        if let Some(anchor) = extras.anchor {
+            commands.entity(entity).insert(StageAnchor { name: anchor });
+        }
+
+        if let Some(light) = extras.light {
+            let [r, g, b] = light.color;
+            commands.entity(entity).with_children(|parent| {
+                parent.spawn(PointLightBundle {
+                    point_light: PointLight {
+                        color: Color::rgb(r, g, b),
+                        intensity: light.intensity,
+                        range: light.range,
+                        ..default()
+                    },
+                    ..default()
+                });
+            });
+        }
+    }
+}
+
+/// 节点及其子孙的网格, 变换到世界坐标后投影到战斗平面
+fn project(
+    root: Entity,
+    plane: &CombatPlane,
+    children: &Query<&Children>,
+    mesh_entities: &MeshEntities,
+    meshes: &Assets<Mesh>,
+) -> Projected {
+    let mut projected = Projected {
+        min: Vec2::splat(f32::MAX),
+        max: Vec2::splat(f32::MIN),
+        ..default()
+    };
+    for entity in std::iter::once(root).chain(children.iter_descendants(root)) {
+        let Ok((transform, mesh_handle, aabb)) = mesh_entities.get(entity) else {
+            continue;
+        };
+        if let Some(aabb) = aabb {
+            let (center, half) = (Vec3::from(aabb.center), Vec3::from(aabb.half_extents));
+            for corner in 0..8 {
+                let sign = Vec3::new(
+                    if corner & 1 == 0 { -1. } else { 1. },
+                    if corner & 2 == 0 { -1. } else { 1. },
+                    if corner & 4 == 0 { -1. } else { 1. },
+                );
+                let point = plane.from_world(transform.transform_point(center + half * sign));
+                projected.min = projected.min.min(point);
+                projected.max = projected.max.max(point);
+            }
+        }
+
+        let Some(mesh) = meshes.get(mesh_handle) else {
+            continue;
+        };
+        let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+            continue;
+        };
+        let offset = projected.vertices.len() as u32;
+        projected.vertices.extend(positions.iter().map(|p| plane.from_world(transform.transform_point(Vec3::from(*p)))));
+        let indices: Vec<u32> = match mesh.indices() {
+            Some(Indices::U16(indices)) => indices.iter().map(|i| *i as u32).collect(),
+            Some(Indices::U32(indices)) => indices.clone(),
+            None => (0..positions.len() as u32).collect(),
+        };
+        projected.triangles.extend(indices.chunks_exact(3).map(|t| [t[0] + offset, t[1] + offset, t[2] + offset]));
+    }
+    projected
+}
+
+fn detect_triggers(
+    mut collisions: EventReader<CollisionEvent>,
+    mut events: EventWriter<TriggerEvent>,
+    triggers: Query<&Trigger>,
+    fighters: Query<(), With<UID>>,
+) {
+    for collision in collisions.iter() {
+        let (a, b, entered) = match *collision {
+            CollisionEvent::Started(a, b, _) => (a, b, true),
+            CollisionEvent::Stopped(a, b, _) => (a, b, false),
+        };
+        for (trigger, fighter) in [(a, b), (b, a)] {
+            let (Ok(trigger), true) = (triggers.get(trigger), fighters.contains(fighter)) else {
+                continue;
+            };
+            events.send(TriggerEvent {
+                trigger: trigger.name.clone(),
+                fighter,
+                entered,
+            });
+        }
+    }
+}
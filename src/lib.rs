@@ -1,30 +1,51 @@
 pub mod plugins;
 pub mod tools;
+pub mod action;
+pub mod loading;
+pub mod billboard;
+pub mod select;
+pub mod round;
+pub mod hud;
+pub mod meter;
+pub mod vfx;
+pub mod audio;
+pub mod fight_camera;
+pub mod stage;
+pub mod parallax;
+pub mod grass;
+pub mod wind;
+pub mod bend;
+pub mod scene_material;
+pub mod level;
 
-use bevy::prelude::{Color, Component, Handle, Image, Material, Mesh, Resource, Scene, States};
+use std::collections::BTreeMap;
+use std::fmt;
+use bevy::prelude::{AlphaMode, Color, Component, Deref, DerefMut, Event, Font, FromWorld, Handle, Image, Material, Mesh, Rect, Reflect, ReflectComponent, Resource, Shader, States, Timer, Vec2, Vec3, World};
 use bevy_asset_loader::prelude::*;
 use bevy::asset::AssetServer;
 use bevy::pbr::{MaterialPipeline, MaterialPipelineKey};
 use bevy::reflect::{TypePath, TypeUuid};
 use bevy::render::mesh::MeshVertexBufferLayout;
-use bevy::render::render_resource::{AsBindGroup, RenderPipelineDescriptor, ShaderRef, SpecializedMeshPipelineError};
+use bevy::render::primitives::Aabb;
+use bevy::sprite::Anchor;
+use bevy::render::render_resource::{AsBindGroup, Buffer, RenderPipelineDescriptor, ShaderDefVal, ShaderRef, SpecializedMeshPipelineError};
+use serde::{Deserialize, Serialize};
 
 #[derive(States, Hash, Clone, PartialEq, Eq, Debug, Default)]
 pub enum GameState {
     #[default]
     Loading,
     Init,
+    CharacterSelect,
+    StageSelect,
     Playing,
 }
 
 
 #[derive(AssetCollection, Resource)]
 pub struct MyAssets {
-    #[asset(path = "textures/background.png")]
-    pub background: Handle<Image>,
-
-    #[asset(path = "textures/tree.png")]
-    pub tree: Handle<Image>,
+    #[asset(path = "fonts/FiraSans-Bold.ttf")]
+    pub font: Handle<Font>,
 
     // #[asset(path = "models/fmj.gltf#Scene0")]
     // pub scene0: Handle<Scene>,
@@ -38,12 +59,657 @@ pub struct MyMaterials {
 #[derive(Component)]
 pub struct MainCamera;
 
+/// 渲染 2D 战斗精灵的相机, 只在 `FightView::Flat` 下启用
+#[derive(Component)]
+pub struct FlatCamera;
+
+/// 场地两侧的墙壁, 角色不能越过, 同时作为相机的边界
+#[derive(Component)]
+pub struct Wall;
+
+/// glTF 场景加载后计算出的包围盒
+#[derive(Resource, Deref, Clone, Copy)]
+pub struct SceneBounds(pub Aabb);
+
+/// 游戏画面在窗口中的区域(逻辑像素), Inspector 打开时只占窗口的一部分, 为空时为整个窗口
+#[derive(Resource, Deref, DerefMut, Default, Clone, Copy)]
+pub struct GameViewport(pub Option<Rect>);
+
+/// 角色唯一标识, 1P = 1, 2P = 2
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct UID(pub u32);
+
+/// 当前键盘控制的角色
+#[derive(Resource)]
+pub struct OwnerUID(pub u32);
+
+#[derive(Component, Deref, Clone, Debug)]
+pub struct CharacterName(pub String);
+
+/// 角色使用的调色板序号, 对应角色数据中的 `palettes`
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Palette(pub usize);
+
+/// 角色生命值
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self { current: max, max }
+    }
+
+    pub fn fraction(&self) -> f32 {
+        (self.current / self.max).clamp(0., 1.)
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new(1000.)
+    }
+}
+
+/// 角色气槽
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component)]
+pub struct Meter {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Meter {
+    pub fn new(max: f32) -> Self {
+        Self { current: 0., max }
+    }
+
+    pub fn fraction(&self) -> f32 {
+        (self.current / self.max).clamp(0., 1.)
+    }
+}
+
+impl Default for Meter {
+    fn default() -> Self {
+        Self::new(100.)
+    }
+}
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Right,
+}
+
+#[derive(Component)]
+pub struct AnimationIndices {
+    pub first: usize,
+    pub last: usize,
+    pub repeat: bool,
+}
+
+#[derive(Component, Deref, DerefMut)]
+pub struct AnimationTimer(pub Timer);
+
+#[derive(Component, Clone, Debug, PartialEq)]
+pub enum CharacterState {
+    Idle,
+    Walk,
+    Action(String),
+    Hit {
+        attack_action: String,
+        hit_action: String,
+    },
+}
+
+/// 状态对应的动作名
+impl fmt::Display for CharacterState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CharacterState::Idle => write!(f, "idle"),
+            CharacterState::Walk => write!(f, "walk"),
+            CharacterState::Action(action_name) => write!(f, "{action_name}"),
+            CharacterState::Hit { hit_action, .. } => write!(f, "{hit_action}"),
+        }
+    }
+}
+
+#[derive(Event, Clone, Debug)]
+pub enum GameEvent {
+    Idle(UID),
+    Left(UID),
+    Right(UID),
+    Action(UID, String),
+    Stop(UID),
+    Hit {
+        uid: UID,
+        attacker: UID,
+        direction: Direction,
+        attack_action: String,
+        hit_action: String,
+        impulse: Option<Vec2>,
+        damage: f32,
+        /// 攻击框和受击框重叠区域的中心
+        position: Vec2,
+    },
+}
+
+/// `state()` 实际结算后的战斗结果, 供 HUD 等表现层使用.
+/// `GameEvent::Hit` 在判定框重叠的每一帧都会发送, 这里每次命中只发送一次
+#[derive(Event, Clone, Debug)]
+pub enum CombatEvent {
+    Damage {
+        uid: UID,
+        attacker: UID,
+        attack_action: String,
+        damage: f32,
+        position: Vec2,
+    },
+    /// 攻击打在防御框上
+    Block {
+        uid: UID,
+        attacker: UID,
+        attack_action: String,
+        position: Vec2,
+    },
+    KO(UID),
+    /// 回合结束, 没有胜者时为平局; `timeout` 为时间耗尽, 否则为 KO
+    RoundOver {
+        winner: Option<UID>,
+        timeout: bool,
+    },
+}
+
+/// 按键指令, 映射到角色数据中的动作名
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CMD {
+    J,
+    K,
+    I,
+    /// 超必杀
+    U,
+}
+
+/// 动作帧阶段: 起手 / 判定 / 收招, 只有收招阶段可以接下一个动作
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ActionStage {
+    #[default]
+    Startup,
+    Active,
+    Recovery,
+}
+
+/// 相对角色中心的矩形, 朝右时的坐标
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Rectbox {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+/// 攻击判定框
+#[derive(Component, Deref, Clone, Copy, Debug)]
+pub struct Hitbox(pub Rectbox);
+
+/// 受击判定框
+#[derive(Component, Deref, Clone, Copy, Debug)]
+pub struct Hurtbox(pub Rectbox);
+
+/// 防御判定框; 攻击框先碰到防御框时这次攻击被防御, 不造成伤害(见 `action::damage`)
+#[derive(Component, Deref, Clone, Copy, Debug)]
+pub struct Blockbox(pub Rectbox);
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Frame {
+    pub hurtbox: Rectbox,
+    #[serde(default)]
+    pub hitbox: Option<Rectbox>,
+    #[serde(default)]
+    pub blockbox: Option<Rectbox>,
+    #[serde(default)]
+    pub stage: ActionStage,
+    /// 帧图片中与角色原点对齐的像素坐标(以左上角为原点), 为空时对齐图片中心
+    #[serde(default)]
+    pub anchor: Option<Vec2>,
+    /// 播放到这一帧时的音效, 如起手的挥空声
+    #[serde(default)]
+    pub sound: Option<String>,
+    /// 加载时按图集计算的锚点, `anchor` 为空时使用, 不写回文件
+    #[serde(skip)]
+    pub default_anchor: Option<Vec2>,
+    /// 加载时为补齐帧数复制的帧, 编辑后不再算作补齐; 保存时去掉末尾的补齐帧
+    #[serde(skip)]
+    pub padding: bool,
+}
+
+impl Frame {
+    /// 与角色原点对齐的像素坐标: 数据中的锚点, 没有时为自动计算的锚点
+    pub fn origin(&self) -> Option<Vec2> {
+        self.anchor.or(self.default_anchor)
+    }
+
+    /// 把像素锚点换算成精灵锚点; 水平翻转(朝左)时图片左右镜像, 锚点也要镜像
+    pub fn sprite_anchor(&self, frame_size: Vec2, flip_x: bool) -> Anchor {
+        let Some(anchor) = self.origin() else {
+            return Anchor::Center;
+        };
+        let x = anchor.x / frame_size.x - 0.5;
+        let y = 0.5 - anchor.y / frame_size.y;
+        Anchor::Custom(Vec2::new(if flip_x { -x } else { x }, y))
+    }
+}
+
+/// 动作精灵图来源
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum SpriteSource {
+    /// 按 columns x rows 网格切分的精灵图, padding 为格子间距, offset 为左上角偏移
+    Sheet {
+        path: String,
+        tile_size: Vec2,
+        columns: usize,
+        rows: usize,
+        #[serde(default)]
+        padding: Option<Vec2>,
+        #[serde(default)]
+        offset: Option<Vec2>,
+    },
+    /// 目录下每帧一张 png, 按文件名排序后打包成图集
+    Folder(String),
+}
+
+impl SpriteSource {
+    pub fn tile_size(&self) -> Option<Vec2> {
+        match self {
+            SpriteSource::Sheet { tile_size, .. } => Some(*tile_size),
+            SpriteSource::Folder(_) => None,
+        }
+    }
+
+    /// 图片或目录路径, 同时作为特效图集的键
+    pub fn path(&self) -> &str {
+        match self {
+            SpriteSource::Sheet { path, .. } => path,
+            SpriteSource::Folder(path) => path,
+        }
+    }
+}
+
+/// 命中/防御时在接触点播放一次的特效动画
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Effect {
+    pub sprite: SpriteSource,
+    /// 播放时长(秒)
+    pub duration: f32,
+    /// 缩放, 为空时为 1
+    #[serde(default)]
+    pub scale: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Action {
+    pub sprite: SpriteSource,
+    /// 帧数据, 少于切分出的帧数时加载时自动补齐
+    #[serde(default)]
+    pub frames: Vec<Frame>,
+    /// 整个动作的时长(秒)
+    pub duration: f32,
+    #[serde(default)]
+    pub repeat: bool,
+    /// 连招的下一个动作
+    #[serde(default)]
+    pub next_action: Option<String>,
+    /// 命中对方后对方播放的动作
+    #[serde(default)]
+    pub hit_action: Option<String>,
+    /// 动作开始时作用于自身的冲量
+    #[serde(default)]
+    pub internal_impulse: Option<Vec2>,
+    /// 命中时作用于对方的冲量
+    #[serde(default)]
+    pub external_impulse: Option<Vec2>,
+    /// 命中时对方损失的生命值
+    #[serde(default)]
+    pub damage: f32,
+    /// 命中时自己获得的气
+    #[serde(default)]
+    pub meter_gain: f32,
+    /// 命中时对方获得的气
+    #[serde(default)]
+    pub victim_meter_gain: f32,
+    /// 发动消耗的气, 气不足时不能发动
+    #[serde(default)]
+    pub meter_cost: f32,
+    /// 按住 EX 键时替换成的强化版动作
+    #[serde(default)]
+    pub ex_action: Option<String>,
+    /// 发动时全场定格的时长(秒), 用于超必杀演出, 0 为不定格
+    #[serde(default)]
+    pub super_freeze: f32,
+    /// 命中特效
+    #[serde(default)]
+    pub hit_effect: Option<Effect>,
+    /// 被防御时的特效
+    #[serde(default)]
+    pub block_effect: Option<Effect>,
+    /// 命中音效
+    #[serde(default)]
+    pub hit_sound: Option<String>,
+    /// 被防御时的音效
+    #[serde(default)]
+    pub block_sound: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, TypeUuid, TypePath)]
+#[uuid = "6a1f3c52-93a4-4d57-9a0e-3f8f0f4f7e21"]
+pub struct Character {
+    pub name: String,
+    /// 选人界面的头像, 为空时使用 idle 动作的第一帧
+    #[serde(default)]
+    pub portrait: Option<String>,
+    /// 调色板贴图路径, 每个一种配色: N x 2 的 png, 第一行原色, 第二行替换色
+    #[serde(default)]
+    pub palettes: Vec<String>,
+    /// 按名字排序, 保存时输出稳定
+    pub actions: BTreeMap<String, Action>,
+    pub commands: BTreeMap<CMD, String>,
+}
+
+/// 场地数据, 从 `assets/stages/*.stage.ron` 加载. 坐标都是 2D 战斗坐标(像素)
+#[derive(Serialize, Deserialize, Clone, Debug, TypeUuid, TypePath)]
+#[uuid = "2d8c7e14-5b3a-4f0e-8a61-c4e9d27b5f93"]
+pub struct Stage {
+    pub name: String,
+    /// 选场地界面的预览图
+    #[serde(default)]
+    pub preview: Option<String>,
+    /// glTF 场景路径, 可以用 `#Scene1` 指定第几个场景
+    #[serde(default)]
+    pub scene: Option<String>,
+    /// 从其他 glTF 文件加载的道具
+    #[serde(default)]
+    pub props: Vec<StageProp>,
+    /// 地面顶部的高度, 对齐到 3D 场景的 y = 0
+    pub floor: f32,
+    /// 左右墙壁内侧的 x 坐标
+    pub walls: (f32, f32),
+    /// 地面摩擦系数, 为空时为 2
+    #[serde(default)]
+    pub friction: Option<f32>,
+    /// 地面弹性系数, 为空时为 0
+    #[serde(default)]
+    pub restitution: Option<f32>,
+    /// 每个玩家的开场位置, 依次对应 1P, 2P
+    #[serde(default)]
+    pub spawns: Vec<Vec2>,
+    /// 场景中的公告板装饰
+    #[serde(default)]
+    pub decorations: Vec<Decoration>,
+    /// 2D 视图的视差背景, 从远到近排列
+    #[serde(default)]
+    pub parallax: Vec<ParallaxLayer>,
+    /// 在 glTF 场景的网格上生成的草地
+    #[serde(default)]
+    pub grass: Vec<GrassPatch>,
+    /// glTF 场景的材质替换, 按顺序匹配, 第一条匹配的生效
+    #[serde(default)]
+    pub materials: Vec<MaterialOverride>,
+    /// 背景音乐路径, 为空时不播放
+    #[serde(default)]
+    pub music: Option<String>,
+    #[serde(default)]
+    pub lighting: Lighting,
+}
+
+/// 没有场地数据时使用的空场地
+impl Default for Stage {
+    fn default() -> Self {
+        Self {
+            name: "training".to_string(),
+            preview: None,
+            scene: None,
+            props: Vec::new(),
+            floor: -230.,
+            walls: (-470., 470.),
+            friction: None,
+            restitution: None,
+            spawns: Vec::new(),
+            decorations: Vec::new(),
+            parallax: Vec::new(),
+            grass: Vec::new(),
+            materials: Vec::new(),
+            music: None,
+            lighting: Lighting::default(),
+        }
+    }
+}
+
+impl Stage {
+    /// 角色开场位置: 没有配置时 1P 在左, 2P 在右
+    pub fn spawn_point(&self, uid: UID) -> Vec3 {
+        let position = self.spawns.get(uid.0 as usize - 1).copied().unwrap_or_else(|| {
+            let x = if uid.0 % 2 == 1 { -200. } else { 200. };
+            Vec2::new(x, self.floor + 230.)
+        });
+        position.extend(1.)
+    }
+}
+
+/// 场景中的一个 glTF 道具, 位置为 3D 世界坐标
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StageProp {
+    /// glTF 场景路径, 可以用 `#Scene1` 指定第几个场景
+    pub scene: String,
+    #[serde(default)]
+    pub position: Vec3,
+    /// 欧拉角(度): 俯仰, 偏航, 翻滚
+    #[serde(default)]
+    pub rotation: Vec3,
+    #[serde(default = "StageProp::default_scale")]
+    pub scale: Vec3,
+}
+
+impl StageProp {
+    fn default_scale() -> Vec3 {
+        Vec3::ONE
+    }
+}
+
+/// 场景中的一张公告板图片, 位置为 3D 世界坐标
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Decoration {
+    pub image: String,
+    pub position: Vec3,
+    #[serde(default = "Decoration::default_scale")]
+    pub scale: Vec3,
+    pub pixels_per_metre: f32,
+    /// 用 `CustomMaterial` 绘制, 随风摇摆并按高度渐变着色; 为空时按原图绘制
+    #[serde(default)]
+    pub foliage: Option<Foliage>,
+}
+
+/// 植物装饰的颜色, 与贴图相乘
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Foliage {
+    pub color: Color,
+    pub tip_color: Color,
+}
+
+impl Decoration {
+    fn default_scale() -> Vec3 {
+        Vec3::ONE
+    }
+}
+
+/// 长草的网格, 按 glTF 中的网格名字查找
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct GrassPatch {
+    pub surface: String,
+    #[serde(default)]
+    pub field: grass::GrassField,
+}
+
+/// glTF 网格的材质替换. 按 Blender 中的自定义属性 `material`(导出为 glTF extras)匹配 `tag`,
+/// 或者按节点、网格的名字匹配 `names`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MaterialOverride {
+    #[serde(default)]
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub names: Vec<String>,
+    pub material: SceneMaterial,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub enum SceneMaterial {
+    /// 换成 `CustomMaterial`, 随风摇摆并按高度渐变着色
+    Foliage {
+        color: Color,
+        tip_color: Color,
+        /// 网格局部 y 坐标从底部到顶部的范围, 为空时为 (-1, 1)
+        #[serde(default)]
+        height_range: Option<Vec2>,
+        #[serde(default)]
+        roughness: Option<f32>,
+        #[serde(default)]
+        translucency: Option<f32>,
+    },
+    /// 修改 glTF 自带的 `StandardMaterial`, 为空的参数保持不变
+    Standard {
+        #[serde(default)]
+        base_color: Option<Color>,
+        #[serde(default)]
+        emissive: Option<Color>,
+        #[serde(default)]
+        perceptual_roughness: Option<f32>,
+        #[serde(default)]
+        metallic: Option<f32>,
+        #[serde(default)]
+        unlit: Option<bool>,
+    },
+}
+
+/// 视差背景层, 跟随 2D 相机按比例移动
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ParallaxLayer {
+    pub image: String,
+    /// 随相机移动的比例: 0 固定在屏幕上(最远), 1 与地面同步
+    pub factor: f32,
+    /// 图片中心的高度
+    #[serde(default)]
+    pub y: f32,
+    #[serde(default = "ParallaxLayer::default_scale")]
+    pub scale: f32,
+    /// 水平方向重复铺满屏幕
+    #[serde(default)]
+    pub repeat: bool,
+    /// 自动滚动速度(像素/秒), 需要 `repeat`
+    #[serde(default)]
+    pub scroll: f32,
+}
+
+impl ParallaxLayer {
+    fn default_scale() -> f32 {
+        1.
+    }
+}
+
+/// 场地灯光, 为空的部分使用 glTF 场景自带的灯光或默认灯光
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Lighting {
+    #[serde(default)]
+    pub sun: Option<Sun>,
+    #[serde(default)]
+    pub ambient: Option<Ambient>,
+}
+
+/// 平行光
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Sun {
+    pub color: Color,
+    pub illuminance: f32,
+    /// 欧拉角(度): 俯仰, 偏航
+    pub rotation: Vec2,
+    #[serde(default)]
+    pub shadows: bool,
+}
+
+/// 环境光
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Ambient {
+    pub color: Color,
+    pub brightness: f32,
+}
+
 // This is the struct that will be passed to your shader
+/// 纯色或贴图材质, 从 `color` 到 `tip_color` 按高度渐变, 随风摇摆.
+/// 网格有 UV 时采样 `texture`(没有贴图时为白色), 有顶点色时乘上顶点色.
+/// 受场景光照影响, 逆光时透光; 投射阴影时同样摇摆(见 `shaders/custom_prepass.wgsl`)
 #[derive(AsBindGroup, Debug, Clone, TypeUuid, TypePath)]
 #[uuid = "f690fdae-d598-45ab-8225-97e2a3f056e0"]
 pub struct CustomMaterial {
+    /// 底部颜色
     #[uniform(0)]
     pub color: Color,
+    /// 顶部颜色
+    #[uniform(0)]
+    pub tip_color: Color,
+    /// 网格局部 y 坐标从底部到顶部的范围, 用于颜色渐变和风的高度比例
+    #[uniform(0)]
+    pub height_range: Vec2,
+    /// 透明度低于此值的像素被丢弃, 有贴图时按此值做镂空
+    #[uniform(0)]
+    pub alpha_cutoff: f32,
+    /// 粗糙度, 叶片一般比较粗糙
+    #[uniform(0)]
+    pub roughness: f32,
+    /// 逆光时透过叶片的光的比例
+    #[uniform(0)]
+    pub translucency: f32,
+    /// 由 `wind::Wind` 同步, 不需要手动设置
+    #[uniform(0)]
+    pub wind: wind::WindUniform,
+    /// 共用的 `bend::BendBuffer`
+    #[storage(3, read_only, buffer)]
+    pub benders: Buffer,
+    #[texture(1)]
+    #[sampler(2)]
+    pub texture: Option<Handle<Image>>,
+}
+
+impl CustomMaterial {
+    /// 默认参数; 所有材质共用压草的缓冲, 所以不实现 `Default`
+    pub fn new(benders: &bend::BendBuffer) -> Self {
+        Self {
+            color: Color::WHITE,
+            tip_color: Color::WHITE,
+            height_range: Vec2::new(-1., 1.),
+            alpha_cutoff: 0.5,
+            roughness: 0.8,
+            translucency: 0.4,
+            wind: wind::WindUniform::default(),
+            benders: (**benders).clone(),
+            texture: None,
+        }
+    }
+}
+
+/// `CustomMaterial` 和草地的着色器导入的模块(`mia::*`), 需要一直保持加载; 新的导入模块也在这里加载
+#[derive(Resource)]
+pub struct CustomMaterialShaders(#[allow(dead_code)] Vec<Handle<Shader>>);
+
+impl FromWorld for CustomMaterialShaders {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        Self(vec![
+            asset_server.load("shaders/custom_material.wgsl"),
+            asset_server.load("shaders/foliage.wgsl"),
+            asset_server.load("shaders/wind.wgsl"),
+            asset_server.load("shaders/bend.wgsl"),
+            asset_server.load("shaders/grass_blade.wgsl"),
+        ])
+    }
 }
 
 impl Material for CustomMaterial {
@@ -53,19 +719,47 @@ impl Material for CustomMaterial {
     fn fragment_shader() -> ShaderRef {
         "shaders/custom_vertex_attribute.wgsl".into()
     }
+    fn prepass_vertex_shader() -> ShaderRef {
+        "shaders/custom_prepass.wgsl".into()
+    }
+    fn prepass_fragment_shader() -> ShaderRef {
+        "shaders/custom_prepass.wgsl".into()
+    }
+
+    fn alpha_mode(&self) -> AlphaMode {
+        match self.texture {
+            Some(_) => AlphaMode::Mask(self.alpha_cutoff),
+            None => AlphaMode::Opaque,
+        }
+    }
 
+    /// 按网格实际带有的属性选择顶点布局
     fn specialize(
         _pipeline: &MaterialPipeline<Self>,
         descriptor: &mut RenderPipelineDescriptor,
         layout: &MeshVertexBufferLayout,
         _key: MaterialPipelineKey<Self>,
     ) -> Result<(), SpecializedMeshPipelineError> {
-        let vertex_layout = layout.get_layout(&[
-            Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
-            // Mesh::ATTRIBUTE_COLOR.at_shader_location(1),
-            // ATTRIBUTE_BLEND_COLOR.at_shader_location(1),
-        ])?;
+        let mut attributes = vec![Mesh::ATTRIBUTE_POSITION.at_shader_location(0)];
+        let mut shader_defs: Vec<ShaderDefVal> = Vec::new();
+        if layout.contains(Mesh::ATTRIBUTE_UV_0) {
+            attributes.push(Mesh::ATTRIBUTE_UV_0.at_shader_location(1));
+            shader_defs.push("VERTEX_UVS".into());
+        }
+        if layout.contains(Mesh::ATTRIBUTE_COLOR) {
+            attributes.push(Mesh::ATTRIBUTE_COLOR.at_shader_location(2));
+            shader_defs.push("VERTEX_COLORS".into());
+        }
+        if layout.contains(Mesh::ATTRIBUTE_NORMAL) {
+            attributes.push(Mesh::ATTRIBUTE_NORMAL.at_shader_location(3));
+            shader_defs.push("VERTEX_NORMALS".into());
+        }
+        let vertex_layout = layout.get_layout(&attributes)?;
         descriptor.vertex.buffers = vec![vertex_layout];
+        descriptor.vertex.shader_defs.extend(shader_defs.iter().cloned());
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader_defs.extend(shader_defs);
+        }
         Ok(())
     }
 }
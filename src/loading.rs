@@ -0,0 +1,357 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use bevy::asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset, LoadState};
+use bevy::math::vec2;
+use bevy::prelude::*;
+use bevy_asset_loader::prelude::*;
+use crate::{Action, Character, Frame, GameState, SpriteSource, Stage};
+
+/// 加载 `assets/characters/*.character.ron` 角色数据
+#[derive(Default)]
+pub struct CharacterLoader;
+
+impl AssetLoader for CharacterLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let character = ron::de::from_bytes::<Character>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(character));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["character.ron"]
+    }
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct CharacterAssets {
+    #[asset(path = "characters", collection(typed))]
+    pub characters: Vec<Handle<Character>>,
+}
+
+/// 加载 `assets/stages/*.stage.ron` 场地数据
+#[derive(Default)]
+pub struct StageLoader;
+
+impl AssetLoader for StageLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let stage = ron::de::from_bytes::<Stage>(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(stage));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["stage.ron"]
+    }
+}
+
+#[derive(AssetCollection, Resource)]
+pub struct StageAssets {
+    #[asset(path = "stages", collection(typed))]
+    pub stages: Vec<Handle<Stage>>,
+}
+
+/// 角色名 -> 角色数据
+#[derive(Resource, Deref, DerefMut, Default)]
+pub struct Characters(pub HashMap<String, Character>);
+
+/// 角色名 -> 动作名 -> 动作精灵图集(原色), 以及按调色板换色后的图集
+#[derive(Resource, Default)]
+pub struct CharactersTextureAtlas {
+    pub atlases: HashMap<String, HashMap<String, Handle<TextureAtlas>>>,
+    /// (角色名, 调色板序号) -> 动作名 -> 换色后的图集
+    pub palettes: HashMap<(String, usize), HashMap<String, Handle<TextureAtlas>>>,
+}
+
+impl CharactersTextureAtlas {
+    /// 按调色板换色后的动作图集; 角色没有这个调色板或调色板加载失败时为原色
+    pub fn get_action(&self, character_name: &str, palette: usize, action_name: &str) -> Option<&Handle<TextureAtlas>> {
+        self.palettes
+            .get(&(character_name.to_string(), palette))
+            .or_else(|| self.atlases.get(character_name))?
+            .get(action_name)
+    }
+}
+
+/// 特效图片路径 -> 特效图集
+#[derive(Resource, Deref, DerefMut, Default)]
+pub struct EffectsTextureAtlas(pub HashMap<String, Handle<TextureAtlas>>);
+
+/// 动作精灵图的图片句柄
+enum ActionSprites {
+    Sheet(Handle<Image>),
+    Folder(Vec<Handle<Image>>),
+}
+
+impl ActionSprites {
+    fn handles(&self) -> &[Handle<Image>] {
+        match self {
+            ActionSprites::Sheet(texture) => std::slice::from_ref(texture),
+            ActionSprites::Folder(frames) => frames,
+        }
+    }
+}
+
+/// 等待加载完成的图片
+#[derive(Resource)]
+pub struct PendingCharacterSprites {
+    /// (角色名, 动作名, 图片)
+    actions: Vec<(String, String, ActionSprites)>,
+    /// (特效精灵, 图片), 按路径去重
+    effects: Vec<(SpriteSource, ActionSprites)>,
+    /// (角色名, 调色板序号, 调色板贴图)
+    palettes: Vec<(String, usize, Handle<Image>)>,
+}
+
+pub fn load_character_sprites(
+    mut commands: Commands,
+    character_assets: Res<CharacterAssets>,
+    assets: Res<Assets<Character>>,
+    asset_server: Res<AssetServer>,
+) {
+    let mut actions = Vec::new();
+    let mut effects: Vec<(SpriteSource, ActionSprites)> = Vec::new();
+    let mut palettes = Vec::new();
+    for handle in &character_assets.characters {
+        let Some(character) = assets.get(handle) else {
+            continue;
+        };
+        for (index, path) in character.palettes.iter().enumerate() {
+            palettes.push((character.name.clone(), index, asset_server.load(path.as_str())));
+        }
+        for (action_name, action) in &character.actions {
+            actions.push((character.name.clone(), action_name.clone(), load_sprites(&asset_server, &action.sprite)));
+            for effect in action.hit_effect.iter().chain(&action.block_effect) {
+                if effects.iter().all(|(sprite, _)| sprite.path() != effect.sprite.path()) {
+                    effects.push((effect.sprite.clone(), load_sprites(&asset_server, &effect.sprite)));
+                }
+            }
+        }
+    }
+    commands.insert_resource(PendingCharacterSprites { actions, effects, palettes });
+}
+
+fn load_sprites(asset_server: &AssetServer, sprite: &SpriteSource) -> ActionSprites {
+    match sprite {
+        SpriteSource::Sheet { path, .. } => ActionSprites::Sheet(asset_server.load(path.as_str())),
+        SpriteSource::Folder(path) => ActionSprites::Folder(load_frame_folder(asset_server, path)),
+    }
+}
+
+/// 加载目录下所有 png, 按文件名排序作为帧顺序
+fn load_frame_folder(asset_server: &AssetServer, path: &str) -> Vec<Handle<Image>> {
+    let handles = match asset_server.load_folder(path) {
+        Ok(handles) => handles,
+        Err(err) => {
+            warn!("failed to load frame folder {}: {:?}", path, err);
+            return Vec::new();
+        }
+    };
+    let mut frames: Vec<(PathBuf, Handle<Image>)> = handles
+        .into_iter()
+        .filter_map(|handle| {
+            let path = asset_server.get_handle_path(handle.id())?.path().to_path_buf();
+            let is_png = path.extension().is_some_and(|extension| extension == "png");
+            is_png.then(|| (path, handle.typed::<Image>()))
+        })
+        .collect();
+    frames.sort_by(|(a, _), (b, _)| a.cmp(b));
+    frames.into_iter().map(|(_, handle)| handle).collect()
+}
+
+/// 图片加载完成后切分/打包图集, 补齐帧数据, 然后进入选人界面
+#[allow(clippy::too_many_arguments)]
+pub fn build_characters(
+    mut commands: Commands,
+    pending: Res<PendingCharacterSprites>,
+    character_assets: Res<CharacterAssets>,
+    assets: Res<Assets<Character>>,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    let loading = pending.actions.iter()
+        .map(|(_, _, sprites)| sprites)
+        .chain(pending.effects.iter().map(|(_, sprites)| sprites))
+        .flat_map(|sprites| sprites.handles())
+        .chain(pending.palettes.iter().map(|(_, _, palette)| palette))
+        .any(|handle| matches!(asset_server.get_load_state(handle), LoadState::NotLoaded | LoadState::Loading));
+    if loading {
+        return;
+    }
+
+    let mut characters = Characters::default();
+    for handle in &character_assets.characters {
+        if let Some(character) = assets.get(handle) {
+            characters.insert(character.name.clone(), character.clone());
+        }
+    }
+
+    let mut characters_texture_atlas = CharactersTextureAtlas::default();
+    // 有动作的图集不能用的角色整个不加载, 以免对战中取不到图集或帧
+    let mut broken = HashSet::new();
+    for (character_name, action_name, sprites) in &pending.actions {
+        let Some(action) = characters.get_mut(character_name).and_then(|character| character.actions.get_mut(action_name)) else {
+            continue;
+        };
+        let Some((texture_atlas, anchors)) = build_atlas(sprites, &action.sprite, &mut images) else {
+            error!("failed to build atlas of {}/{} from {}", character_name, action_name, action.sprite.path());
+            broken.insert(character_name.clone());
+            continue;
+        };
+        if texture_atlas.is_empty() || action.frames.len() > texture_atlas.len() {
+            error!(
+                "{}/{} declares {} frames but {} has {}",
+                character_name,
+                action_name,
+                action.frames.len(),
+                action.sprite.path(),
+                texture_atlas.len()
+            );
+            broken.insert(character_name.clone());
+            continue;
+        }
+        fill_frames(action, &anchors);
+        characters_texture_atlas
+            .atlases
+            .entry(character_name.clone())
+            .or_default()
+            .insert(action_name.clone(), texture_atlases.add(texture_atlas));
+    }
+
+    for character_name in &broken {
+        error!("character {} is not loaded", character_name);
+        characters.remove(character_name);
+        characters_texture_atlas.atlases.remove(character_name);
+    }
+
+    // 换色在 CPU 上完成, 2D 精灵、公告板和选人预览使用同样的换色图集
+    for (character_name, index, palette) in &pending.palettes {
+        let Some(palette_image) = images.get(palette).cloned() else {
+            warn!("failed to load palette {} of {}", index, character_name);
+            continue;
+        };
+        let Some(atlases) = characters_texture_atlas.atlases.get(character_name) else {
+            continue;
+        };
+        let mut swapped = HashMap::new();
+        for (action_name, handle) in atlases {
+            let Some(mut texture_atlas) = texture_atlases.get(handle).cloned() else {
+                continue;
+            };
+            let Some(image) = images.get(&texture_atlas.texture).and_then(|image| swap_palette(image, &palette_image)) else {
+                warn!("failed to apply palette {} to {}/{}", index, character_name, action_name);
+                continue;
+            };
+            texture_atlas.texture = images.add(image);
+            swapped.insert(action_name.clone(), texture_atlases.add(texture_atlas));
+        }
+        characters_texture_atlas.palettes.insert((character_name.clone(), *index), swapped);
+    }
+
+    let mut effects_texture_atlas = EffectsTextureAtlas::default();
+    for (sprite, sprites) in &pending.effects {
+        let Some((texture_atlas, _)) = build_atlas(sprites, sprite, &mut images) else {
+            warn!("failed to build effect atlas from {}", sprite.path());
+            continue;
+        };
+        effects_texture_atlas.insert(sprite.path().to_string(), texture_atlases.add(texture_atlas));
+    }
+
+    for name in characters.keys() {
+        info!("character loaded: {}", name);
+    }
+    commands.insert_resource(characters);
+    commands.insert_resource(characters_texture_atlas);
+    commands.insert_resource(effects_texture_atlas);
+    commands.remove_resource::<PendingCharacterSprites>();
+    game_state.set(GameState::CharacterSelect);
+}
+
+/// 与调色板第一行某个颜色相近的像素换成第二行对应的颜色; 在线性颜色空间中比较
+fn swap_palette(image: &Image, palette: &Image) -> Option<Image> {
+    const TOLERANCE: f32 = 0.02;
+    let (width, height) = (palette.size().x as usize, palette.size().y as usize);
+    if height < 2 || palette.data.len() < width * height * 4 || !image.data.len().is_multiple_of(4) {
+        return None;
+    }
+    let linear = |pixel: &[u8]| Vec3::from_slice(&Color::rgb_u8(pixel[0], pixel[1], pixel[2]).as_linear_rgba_f32()[..3]);
+    let (sources, targets) = palette.data.split_at(width * 4);
+    let sources: Vec<Vec3> = sources.chunks_exact(4).map(linear).collect();
+    let mut image = image.clone();
+    for pixel in image.data.chunks_exact_mut(4) {
+        let color = linear(pixel);
+        if let Some(index) = sources.iter().position(|source| source.distance(color) < TOLERANCE) {
+            pixel[..3].copy_from_slice(&targets[index * 4..index * 4 + 3]);
+        }
+    }
+    Some(image)
+}
+
+/// 切分精灵图或打包逐帧图片, 返回图集和每帧的默认锚点
+fn build_atlas(sprites: &ActionSprites, sprite: &SpriteSource, images: &mut Assets<Image>) -> Option<(TextureAtlas, Vec<Vec2>)> {
+    match (sprites, sprite) {
+        (ActionSprites::Sheet(texture), SpriteSource::Sheet { tile_size, columns, rows, padding, offset, .. }) => {
+            let texture_atlas = TextureAtlas::from_grid(texture.clone(), *tile_size, *columns, *rows, *padding, *offset);
+            let anchors = vec![*tile_size / 2.; texture_atlas.len()];
+            Some((texture_atlas, anchors))
+        }
+        (ActionSprites::Folder(frames), SpriteSource::Folder(_)) => pack_frames(frames, images),
+        _ => None,
+    }
+}
+
+/// 把逐帧图片打包成一张图集, 返回按帧顺序排列的图集和每帧锚点
+fn pack_frames(frames: &[Handle<Image>], images: &mut Assets<Image>) -> Option<(TextureAtlas, Vec<Vec2>)> {
+    let mut builder = TextureAtlasBuilder::default();
+    let mut sizes = Vec::new();
+    for handle in frames {
+        let Some(image) = images.get(handle) else {
+            warn!("frame image not loaded: {:?}", handle);
+            continue;
+        };
+        builder.add_texture(handle.clone(), image);
+        sizes.push((handle, image.size()));
+    }
+    if sizes.is_empty() {
+        return None;
+    }
+    let packed = builder.finish(images).map_err(|err| warn!("{:?}", err)).ok()?;
+
+    // 打包后的矩形顺序与帧顺序无关, 按帧顺序重排
+    let mut texture_atlas = TextureAtlas::new_empty(packed.texture.clone(), packed.size);
+    for (handle, _) in &sizes {
+        texture_atlas.add_texture(packed.textures[packed.get_texture_index(handle)?]);
+    }
+
+    // 帧尺寸不一致时以最大帧的底边中点对齐, 切换帧时角色不会漂移
+    let max_height = sizes.iter().map(|(_, size)| size.y).fold(0., f32::max);
+    let anchors = sizes.iter().map(|(_, size)| vec2(size.x / 2., size.y - max_height / 2.)).collect();
+    Some((texture_atlas, anchors))
+}
+
+/// 按切分出的帧数补齐帧数据, 记下每帧自动计算的锚点; 两者都不写回文件
+fn fill_frames(action: &mut Action, anchors: &[Vec2]) {
+    if action.frames.len() < anchors.len() {
+        let template = Frame {
+            padding: true,
+            ..action.frames.last().cloned().unwrap_or_default()
+        };
+        action.frames.resize(anchors.len(), template);
+    }
+    for (frame, anchor) in action.frames.iter_mut().zip(anchors) {
+        frame.default_anchor = Some(*anchor);
+    }
+}
@@ -5,8 +5,25 @@ use bevy::{
     prelude::*,
 };
 use bevy::window::{WindowMode};
-use mia::{CustomMaterial, GameState, MainCamera, MyMaterials};
+use mia::{CustomMaterial, CustomMaterialShaders, GameState, MainCamera, MyMaterials};
 use mia::plugins::{GamePlugin, InspectPlugin, LoadPlugin};
+use mia::action::ActionPlugin;
+use mia::billboard::BillboardPlugin;
+use mia::select::SelectPlugin;
+use mia::round::RoundPlugin;
+use mia::hud::HudPlugin;
+use mia::meter::MeterPlugin;
+use mia::vfx::VfxPlugin;
+use mia::audio::SoundPlugin;
+use mia::fight_camera::FightCameraPlugin;
+use mia::stage::StagePlugin;
+use mia::parallax::ParallaxPlugin;
+use mia::grass::GrassPlugin;
+use mia::wind::WindPlugin;
+use mia::bend::{BendPlugin, Bender};
+use mia::scene_material::SceneMaterialPlugin;
+use mia::level::LevelPlugin;
+use mia::tools::CameraController;
 
 fn main() {
     App::new()
@@ -17,14 +34,33 @@ fn main() {
                     ..default()
                 }),
                 ..default()
-            }), MaterialPlugin::<CustomMaterial>::default()
+            }), MaterialPlugin::<CustomMaterial>::default(),
         ))
         .add_plugins((
             LoadPlugin,
             InspectPlugin,
             GamePlugin,
+            ActionPlugin,
+            BillboardPlugin,
+            SelectPlugin,
+            RoundPlugin,
+            HudPlugin,
+            MeterPlugin,
+            VfxPlugin,
+            SoundPlugin,
+            FightCameraPlugin,
+        ))
+        .add_plugins((
+            StagePlugin,
+            ParallaxPlugin,
+            GrassPlugin,
+            WindPlugin,
+            BendPlugin,
+            SceneMaterialPlugin,
+            LevelPlugin,
         ))
         .add_state::<GameState>()
+        .init_resource::<CustomMaterialShaders>()
         .add_systems(Startup, setup)
         .run();
 }
@@ -50,7 +86,15 @@ fn setup(
             ..default()
         }),
         ..default()
-    }, MainCamera));
+    }, MainCamera, CameraController {
+        // F9 切换为自由相机
+        enabled: false,
+        ..default()
+    }, Bender {
+        // 自由相机贴近地面时推开草叶
+        radius: 1.,
+        ..default()
+    }));
 
     //
     //
@@ -0,0 +1,97 @@
+//! 气槽: 命中和受击时按动作数据中的 `meter_gain` / `victim_meter_gain` 涨气,
+//! 发动 `meter_cost` 大于 0 的动作(EX 技、超必杀)时在 `state()` 中扣除.
+//! 带 `super_freeze` 的动作发动时全场定格, 只有发动者继续播放动画.
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use crate::{CharacterName, CombatEvent, GameState, Meter, UID};
+use crate::loading::Characters;
+use crate::vfx::Hitstop;
+
+pub struct MeterPlugin;
+
+impl Plugin for MeterPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SuperFreeze>()
+            .add_systems(Update, (gain_meter, tick_freeze).run_if(in_state(GameState::Playing)))
+            .add_systems(OnExit(GameState::Playing), reset_freeze);
+    }
+}
+
+/// 超必杀定格
+#[derive(Resource, Default)]
+pub struct SuperFreeze {
+    /// 发动超必杀的角色, 为空时没有定格
+    pub owner: Option<UID>,
+    timer: Timer,
+}
+
+impl SuperFreeze {
+    pub fn start(&mut self, owner: UID, seconds: f32) {
+        self.owner = Some(owner);
+        self.timer = Timer::from_seconds(seconds, TimerMode::Once);
+    }
+
+    pub fn active(&self) -> bool {
+        self.owner.is_some()
+    }
+
+    /// 定格中, 除发动者以外的角色都被冻结
+    pub fn is_frozen(&self, uid: UID) -> bool {
+        self.owner.is_some_and(|owner| owner != uid)
+    }
+}
+
+fn gain_meter(
+    mut events: EventReader<CombatEvent>,
+    characters: Res<Characters>,
+    mut fighters: Query<(&UID, &CharacterName, &mut Meter)>,
+) {
+    for event in events.iter() {
+        let CombatEvent::Damage { uid, attacker, attack_action, .. } = event else {
+            continue;
+        };
+        let action = fighters
+            .iter()
+            .find(|(fighter, ..)| *fighter == attacker)
+            .and_then(|(_, character_name, _)| characters.get(character_name.as_str()))
+            .and_then(|character| character.actions.get(attack_action));
+        let Some(action) = action else {
+            continue;
+        };
+        for (fighter, _, mut meter) in fighters.iter_mut() {
+            let gain = if fighter == attacker {
+                action.meter_gain
+            } else if fighter == uid {
+                action.victim_meter_gain
+            } else {
+                continue;
+            };
+            meter.current = (meter.current + gain).min(meter.max);
+        }
+    }
+}
+
+/// 定格和顿帧期间暂停物理模拟
+fn tick_freeze(
+    time: Res<Time>,
+    hitstop: Res<Hitstop>,
+    mut freeze: ResMut<SuperFreeze>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    if freeze.active() {
+        freeze.timer.tick(time.delta());
+        if freeze.timer.finished() {
+            freeze.owner = None;
+        }
+    }
+    let active = !freeze.active() && !hitstop.active();
+    if rapier_config.physics_pipeline_active != active {
+        rapier_config.physics_pipeline_active = active;
+    }
+}
+
+fn reset_freeze(mut freeze: ResMut<SuperFreeze>, mut rapier_config: ResMut<RapierConfiguration>) {
+    freeze.owner = None;
+    rapier_config.physics_pipeline_active = true;
+}
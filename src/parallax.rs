@@ -0,0 +1,253 @@
+//! 视差背景: 场地数据中的 `parallax` 层在 2D 视图中画在角色后面, 按各自的比例跟随 2D 相机移动,
+//! 可以水平重复铺满屏幕和自动滚动.
+//! 2.5D 视图中每层是战斗平面后面的 3D 面片, 按 `factor` 换算放在不同的深度, 视差由透视产生.
+
+use bevy::pbr::NotShadowCaster;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use crate::{FlatCamera, GameState, MainCamera, ParallaxLayer};
+use crate::billboard::{CombatPlane, FightView};
+use crate::fight_camera::FightCamera;
+use crate::stage::{CurrentStage, StageEntity};
+
+pub struct ParallaxPlugin;
+
+impl Plugin for ParallaxPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Playing), spawn_layers)
+            .add_systems(Update, (build_layers, build_quads).run_if(in_state(GameState::Playing)))
+            .add_systems(
+                PostUpdate,
+                (follow_camera, follow_main_camera)
+                    .before(bevy::transform::TransformSystem::TransformPropagate)
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// 最远一层的 z, 每层向前 `LAYER_SPACING`; 2D 相机只能看到 z > 0, 角色在 z = 1
+const BACK_Z: f32 = 0.1;
+const LAYER_SPACING: f32 = 0.01;
+
+/// 2.5D 视图中按这个相机距离(米)换算深度: 相机在这个距离时, 面片随相机移动的比例正好是 `factor`
+const REFERENCE_DISTANCE: f32 = 13.;
+/// `factor` 的下限, 为 0 的层放在有限远处
+const MIN_FACTOR: f32 = 0.05;
+/// 重复的层最多的份数, 图片很窄时不会生成过多精灵
+const MAX_REPEATS: usize = 64;
+
+#[derive(Component)]
+struct Parallax {
+    layer: ParallaxLayer,
+    image: Handle<Image>,
+    /// 自动滚动累计的偏移
+    offset: f32,
+    /// 缩放后的图片宽度, 图片加载完成前为空
+    width: Option<f32>,
+}
+
+/// 2.5D 视图中的视差层, 子实体是重复的面片
+#[derive(Component)]
+struct ParallaxQuads {
+    layer: ParallaxLayer,
+    image: Handle<Image>,
+    /// 在战斗平面后面的距离(米)
+    depth: f32,
+    /// 自动滚动累计的偏移(米)
+    offset: f32,
+    /// 面片宽度(米), 图片加载完成前为空
+    width: Option<f32>,
+}
+
+impl ParallaxQuads {
+    fn new(layer: &ParallaxLayer, image: Handle<Image>) -> Self {
+        // 从参考距离看, 深度 d 处的物体随相机移动的比例为 D / (D + d)
+        let depth = REFERENCE_DISTANCE * (1. / layer.factor.clamp(MIN_FACTOR, 1.) - 1.);
+        Self { layer: layer.clone(), image, depth, offset: 0., width: None }
+    }
+
+    /// 放远后放大, 从参考距离看与 2D 视图中的大小一致
+    fn magnification(&self) -> f32 {
+        (REFERENCE_DISTANCE + self.depth) / REFERENCE_DISTANCE
+    }
+}
+
+fn spawn_layers(mut commands: Commands, stage: Res<CurrentStage>, plane: Res<CombatPlane>, asset_server: Res<AssetServer>) {
+    for (index, layer) in stage.parallax.iter().enumerate() {
+        // 宽度为 0 时无法重复和滚动
+        if layer.scale.is_nan() || layer.scale <= 0. {
+            warn!("parallax layer {}: scale must be positive, got {}", layer.image, layer.scale);
+            continue;
+        }
+        let image: Handle<Image> = asset_server.load(layer.image.as_str());
+        let quads = ParallaxQuads::new(layer, image.clone());
+        let center = plane.to_world(Vec2::new(0., layer.y * quads.magnification()));
+        commands.spawn((
+            SpatialBundle::from_transform(Transform::from_xyz(0., center.y, center.z - quads.depth)),
+            quads,
+            StageEntity,
+            Name::new("Parallax 3D"),
+        ));
+        commands.spawn((
+            SpatialBundle::from_transform(Transform::from_xyz(0., layer.y, BACK_Z + index as f32 * LAYER_SPACING)),
+            Parallax {
+                layer: layer.clone(),
+                image,
+                offset: 0.,
+                width: None,
+            },
+            StageEntity,
+            Name::new("Parallax"),
+        ));
+    }
+}
+
+/// 图片加载完成后生成精灵, 重复的层生成足够覆盖最大缩放时屏幕宽度的份数
+fn build_layers(
+    mut commands: Commands,
+    images: Res<Assets<Image>>,
+    fight_camera: Res<FightCamera>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut layers: Query<(Entity, &mut Parallax)>,
+) {
+    let view_width = windows.get_single().map_or(1920., |window| window.width()) * fight_camera.max_zoom;
+    for (entity, mut parallax) in layers.iter_mut() {
+        if parallax.width.is_some() {
+            continue;
+        }
+        let Some(image) = images.get(&parallax.image) else {
+            continue;
+        };
+        let width = image.size().x * parallax.layer.scale;
+        if width.is_nan() || width <= 0. {
+            warn!("parallax layer {}: image has no width", parallax.layer.image);
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let count = if parallax.layer.repeat { ((view_width / width).ceil() as usize + 2).min(MAX_REPEATS) } else { 1 };
+        commands.entity(entity).with_children(|layer| {
+            for i in 0..count {
+                let x = (i as f32 - (count - 1) as f32 / 2.) * width;
+                layer.spawn(SpriteBundle {
+                    texture: parallax.image.clone(),
+                    transform: Transform::from_xyz(x, 0., 0.).with_scale(Vec3::splat(parallax.layer.scale)),
+                    ..default()
+                });
+            }
+        });
+        parallax.width = Some(width);
+    }
+}
+
+/// 图片加载完成后生成面片, 重复的层生成足够覆盖相机最远时视野宽度的份数
+#[allow(clippy::too_many_arguments)]
+fn build_quads(
+    mut commands: Commands,
+    images: Res<Assets<Image>>,
+    plane: Res<CombatPlane>,
+    fight_camera: Res<FightCamera>,
+    cameras: Query<&Projection, With<MainCamera>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut layers: Query<(Entity, &mut ParallaxQuads)>,
+) {
+    for (entity, mut quads) in layers.iter_mut() {
+        if quads.width.is_some() {
+            continue;
+        }
+        let Some(image) = images.get(&quads.image) else {
+            continue;
+        };
+        let size = plane.to_world_size(image.size() * quads.layer.scale) * quads.magnification();
+        if size.x.is_nan() || size.x <= 0. {
+            warn!("parallax layer {}: image has no width", quads.layer.image);
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let count = if quads.layer.repeat {
+            let (fov, aspect) = match cameras.get_single() {
+                Ok(Projection::Perspective(projection)) => (projection.fov, projection.aspect_ratio),
+                _ => (std::f32::consts::FRAC_PI_4, 16. / 9.),
+            };
+            let view_width = 2. * (fight_camera.max_distance + quads.depth) * (fov / 2.).tan() * aspect;
+            ((view_width / size.x).ceil() as usize + 2).min(MAX_REPEATS)
+        } else {
+            1
+        };
+        let mesh = meshes.add(shape::Quad::new(size).into());
+        let material = materials.add(StandardMaterial {
+            base_color_texture: Some(quads.image.clone()),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        });
+        commands.entity(entity).with_children(|layer| {
+            for i in 0..count {
+                let x = (i as f32 - (count - 1) as f32 / 2.) * size.x;
+                layer.spawn((
+                    PbrBundle {
+                        mesh: mesh.clone(),
+                        material: material.clone(),
+                        transform: Transform::from_xyz(x, 0., 0.),
+                        ..default()
+                    },
+                    NotShadowCaster,
+                ));
+            }
+        });
+        quads.width = Some(size.x);
+    }
+}
+
+/// 重复的层按面片宽度对齐到相机下方, 始终铺满视野; 只在 2.5D 视图中显示
+fn follow_main_camera(
+    time: Res<Time>,
+    view: Res<FightView>,
+    plane: Res<CombatPlane>,
+    cameras: Query<&Transform, (With<MainCamera>, Without<ParallaxQuads>)>,
+    mut layers: Query<(&mut ParallaxQuads, &mut Transform, &mut Visibility)>,
+) {
+    let camera = cameras.get_single().map_or(Vec3::ZERO, |camera| camera.translation);
+    let visibility = if *view == FightView::Billboard { Visibility::Inherited } else { Visibility::Hidden };
+    for (mut quads, mut transform, mut layer_visibility) in layers.iter_mut() {
+        if *layer_visibility != visibility {
+            *layer_visibility = visibility;
+        }
+        let Some(width) = quads.width else {
+            continue;
+        };
+        if !quads.layer.repeat {
+            continue;
+        }
+        transform.translation.x = quads.offset + ((camera.x - quads.offset) / width).round() * width;
+        let scroll = plane.to_world_size(Vec2::splat(quads.layer.scroll)).x * quads.magnification();
+        quads.offset = (quads.offset + scroll * time.delta_seconds()).rem_euclid(width);
+    }
+}
+
+fn follow_camera(
+    time: Res<Time>,
+    cameras: Query<&Transform, (With<FlatCamera>, Without<Parallax>)>,
+    mut layers: Query<(&mut Parallax, &mut Transform)>,
+) {
+    let Ok(camera) = cameras.get_single() else {
+        return;
+    };
+    let camera = camera.translation;
+    for (mut parallax, mut transform) in layers.iter_mut() {
+        let Some(width) = parallax.width else {
+            continue;
+        };
+        let layer = &parallax.layer;
+        // 相对相机的位置: 比例越小越像固定在屏幕上
+        let mut x = parallax.offset - camera.x * layer.factor;
+        if layer.repeat {
+            x = (x + width / 2.).rem_euclid(width) - width / 2.;
+        }
+        transform.translation.x = camera.x + x;
+        transform.translation.y = layer.y + camera.y * (1. - layer.factor);
+        if layer.repeat {
+            parallax.offset = (parallax.offset + layer.scroll * time.delta_seconds()).rem_euclid(width);
+        }
+    }
+}
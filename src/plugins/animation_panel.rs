@@ -0,0 +1,77 @@
+//! Inspector 中的 glTF 动画面板: 列出场景的动画, 播放/暂停、切换、拖动时间轴、调整速度和循环.
+//! 快捷键见 `SceneHandle` 的说明: 空格播放/暂停, 回车切换到下一个动画.
+
+use bevy::prelude::*;
+use crate::tools::{SceneAnimations, SceneHandle};
+
+pub fn ui(ui: &mut egui::Ui, world: &mut World) {
+    if world.resource::<SceneAnimations>().clips.is_empty() {
+        ui.label("场景没有动画");
+        return;
+    }
+
+    // 时间轴显示主场景中第一个播放器的进度
+    let instance_id = world.get_resource::<SceneHandle>().and_then(|scene_handle| scene_handle.instance_id());
+    let mut players = world.query::<&AnimationPlayer>();
+    let elapsed = instance_id.and_then(|instance_id| {
+        world
+            .resource::<SceneSpawner>()
+            .iter_instance_entities(instance_id)
+            .find_map(|entity| players.get(world, entity).ok())
+            .map(AnimationPlayer::elapsed)
+    });
+
+    world.resource_scope(|world, mut animations: Mut<SceneAnimations>| {
+        let duration = animations
+            .current_clip()
+            .and_then(|(_, clip)| world.resource::<Assets<AnimationClip>>().get(clip))
+            .map_or(0., AnimationClip::duration);
+
+        ui.horizontal(|ui| {
+            let label = if animations.paused { "播放" } else { "暂停" };
+            if ui.button(label).clicked() {
+                animations.paused = !animations.paused;
+            }
+            if ui.button("下一个").clicked() {
+                animations.next();
+            }
+            let mut looping = animations.looping;
+            if ui.checkbox(&mut looping, "循环").changed() {
+                animations.looping = looping;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("速度");
+            let mut speed = animations.speed;
+            if ui.add(egui::Slider::new(&mut speed, 0.0..=3.0)).changed() {
+                animations.speed = speed;
+            }
+        });
+
+        if let Some(elapsed) = elapsed {
+            ui.horizontal(|ui| {
+                ui.label("时间");
+                // 循环播放时 elapsed 会一直增加, 按动画时长取余
+                let mut time = if duration > 0. && animations.looping { elapsed % duration } else { elapsed.min(duration) };
+                let slider = egui::Slider::new(&mut time, 0.0..=duration.max(0.001)).suffix(" s");
+                if ui.add(slider).changed() {
+                    animations.seek = Some(time);
+                }
+            });
+        }
+
+        ui.separator();
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            let mut selected = None;
+            for (index, (name, _)) in animations.clips.iter().enumerate() {
+                if ui.selectable_label(index == animations.current, name).clicked() {
+                    selected = Some(index);
+                }
+            }
+            if let Some(index) = selected {
+                animations.select(index);
+            }
+        });
+    });
+}
@@ -0,0 +1,224 @@
+//! 战斗调试层, 只在 debug 构建中编译.
+//! 各项开关可以在 Inspector 的 Resources 面板中修改 `DebugOverlay`, 或使用快捷键:
+//! - F1 全部开/关
+//! - F2 受击框 F3 攻击框 F4 防御框 F5 推挤框
+//! - F6 物理碰撞体 F7 状态标签 F8 当前帧序号与阶段
+
+use bevy::math::vec2;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use crate::{Blockbox, CharacterName, CharacterState, FlatCamera, GameState, GameViewport, Hitbox, Hurtbox, MainCamera, MyAssets, Rectbox, UID};
+use crate::loading::Characters;
+use crate::billboard::{CombatPlane, FightView};
+
+pub struct DebugOverlayPlugin;
+
+impl Plugin for DebugOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DebugOverlay>()
+            .register_type::<DebugOverlay>()
+            .add_plugins(RapierDebugRenderPlugin {
+                enabled: false,
+                ..default()
+            })
+            .add_systems(Update, toggle_overlay)
+            .add_systems(Update, sync_collider_render.run_if(resource_changed::<DebugOverlay>()))
+            .add_systems(Update, (spawn_labels, update_labels).chain().run_if(in_state(GameState::Playing)))
+            .add_systems(Last, draw_boxes.run_if(in_state(GameState::Playing)));
+    }
+}
+
+#[derive(Resource, Reflect, Default, Clone)]
+#[reflect(Resource)]
+pub struct DebugOverlay {
+    pub hurtbox: bool,
+    pub hitbox: bool,
+    pub blockbox: bool,
+    /// 角色物理碰撞体的包围盒, 即角色之间互相推挤的范围
+    pub pushbox: bool,
+    /// rapier 的碰撞体线框
+    pub colliders: bool,
+    pub state_labels: bool,
+    pub frame_info: bool,
+}
+
+impl DebugOverlay {
+    fn any(&self) -> bool {
+        self.hurtbox || self.hitbox || self.blockbox || self.pushbox || self.colliders || self.state_labels || self.frame_info
+    }
+
+    fn set_all(&mut self, on: bool) {
+        *self = DebugOverlay {
+            hurtbox: on,
+            hitbox: on,
+            blockbox: on,
+            pushbox: on,
+            colliders: on,
+            state_labels: on,
+            frame_info: on,
+        };
+    }
+}
+
+/// 角色头顶的状态标签, UI 节点按当前视图投影到角色头顶, 两种视图下都能显示
+#[derive(Component)]
+struct StateLabel {
+    fighter: Entity,
+}
+
+/// 标签相对角色原点的高度(像素)
+const LABEL_HEIGHT: f32 = 60.;
+
+/// 按键对应的 `DebugOverlay` 开关
+type OverlayToggle = fn(&mut DebugOverlay) -> &mut bool;
+
+fn toggle_overlay(input: Res<Input<KeyCode>>, mut overlay: ResMut<DebugOverlay>) {
+    if input.just_pressed(KeyCode::F1) {
+        let on = !overlay.any();
+        overlay.set_all(on);
+    }
+    let toggles: [(KeyCode, OverlayToggle); 7] = [
+        (KeyCode::F2, |overlay| &mut overlay.hurtbox),
+        (KeyCode::F3, |overlay| &mut overlay.hitbox),
+        (KeyCode::F4, |overlay| &mut overlay.blockbox),
+        (KeyCode::F5, |overlay| &mut overlay.pushbox),
+        (KeyCode::F6, |overlay| &mut overlay.colliders),
+        (KeyCode::F7, |overlay| &mut overlay.state_labels),
+        (KeyCode::F8, |overlay| &mut overlay.frame_info),
+    ];
+    for (key, flag) in toggles {
+        if input.just_pressed(key) {
+            let flag = flag(&mut overlay);
+            *flag = !*flag;
+        }
+    }
+}
+
+fn sync_collider_render(overlay: Res<DebugOverlay>, mut context: ResMut<DebugRenderContext>) {
+    context.enabled = overlay.colliders;
+}
+
+fn spawn_labels(
+    mut commands: Commands,
+    my_assets: Res<MyAssets>,
+    query: Query<Entity, Added<UID>>,
+) {
+    for fighter in query.iter() {
+        commands.spawn((
+            TextBundle::from_section("", TextStyle {
+                font: my_assets.font.clone(),
+                font_size: 16.0,
+                color: Color::YELLOW,
+            }).with_text_alignment(TextAlignment::Center).with_style(Style {
+                position_type: PositionType::Absolute,
+                ..default()
+            }),
+            Visibility::Hidden,
+            StateLabel { fighter },
+            Name::new("State Label"),
+        ));
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn update_labels(
+    mut commands: Commands,
+    overlay: Res<DebugOverlay>,
+    characters: Res<Characters>,
+    view: Res<FightView>,
+    plane: Res<CombatPlane>,
+    viewport: Res<GameViewport>,
+    fighters: Query<(&CharacterState, &CharacterName, &TextureAtlasSprite, &GlobalTransform)>,
+    main_cameras: Query<(&Camera, &GlobalTransform), (With<MainCamera>, Without<FlatCamera>)>,
+    flat_cameras: Query<(&Camera, &GlobalTransform), (With<FlatCamera>, Without<MainCamera>)>,
+    mut labels: Query<(Entity, &StateLabel, &Node, &mut Style, &mut Text, &mut Visibility)>,
+) {
+    for (entity, label, node, mut style, mut text, mut visibility) in labels.iter_mut() {
+        let Ok((state, character_name, sprite, transform)) = fighters.get(label.fighter) else {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        };
+        if !overlay.state_labels && !overlay.frame_info {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+
+        // UI 以主相机视口为原点, 2D 相机覆盖整个窗口, 需要减去视口偏移
+        let head = transform.translation().truncate() + Vec2::new(0., LABEL_HEIGHT);
+        let position = match *view {
+            FightView::Billboard => main_cameras
+                .get_single()
+                .ok()
+                .and_then(|(camera, camera_transform)| camera.world_to_viewport(camera_transform, plane.to_world(head))),
+            FightView::Flat => flat_cameras
+                .get_single()
+                .ok()
+                .and_then(|(camera, camera_transform)| camera.world_to_viewport(camera_transform, head.extend(0.)))
+                .map(|position| position - viewport.map_or(Vec2::ZERO, |rect| rect.min)),
+        };
+        let Some(position) = position else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        // 标签底边居中对齐头顶
+        let size = node.size();
+        style.left = Val::Px(position.x - size.x / 2.);
+        style.top = Val::Px(position.y - size.y);
+
+        let mut lines = Vec::new();
+        if overlay.state_labels {
+            lines.push(format!("{:?}", state));
+        }
+        if overlay.frame_info {
+            let action = characters.get(character_name.as_str()).and_then(|character| character.actions.get(&state.to_string()));
+            if let Some(action) = action {
+                let stage = action.frames.get(sprite.index).map(|frame| frame.stage).unwrap_or_default();
+                lines.push(format!("{} {}/{} {:?}", state, sprite.index, action.frames.len() - 1, stage));
+            }
+        }
+        text.sections[0].value = lines.join("\n");
+        *visibility = Visibility::Inherited;
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn draw_boxes(
+    overlay: Res<DebugOverlay>,
+    view: Res<FightView>,
+    plane: Res<CombatPlane>,
+    query: Query<(&Transform, Option<&Hurtbox>, Option<&Hitbox>, Option<&Blockbox>, Option<&Collider>), With<UID>>,
+    mut gizmos: Gizmos,
+) {
+    // 2.5D 模式下判定框画在 3D 场景的战斗平面上
+    let plane = (*view == FightView::Billboard).then_some(*plane);
+    for (transform, hurtbox, hitbox, blockbox, collider) in query.iter() {
+        let origin = transform.translation.truncate();
+
+        if let (true, Some(hurtbox)) = (overlay.hurtbox, hurtbox) {
+            draw_rect(&mut gizmos, plane, origin, hurtbox, Color::BLUE);
+        }
+        if let (true, Some(hitbox)) = (overlay.hitbox, hitbox) {
+            draw_rect(&mut gizmos, plane, origin, hitbox, Color::RED);
+        }
+        if let (true, Some(blockbox)) = (overlay.blockbox, blockbox) {
+            draw_rect(&mut gizmos, plane, origin, blockbox, Color::WHITE);
+        }
+        if let (true, Some(collider)) = (overlay.pushbox, collider) {
+            let aabb = collider.raw.compute_local_aabb();
+            let pushbox = Rectbox {
+                min: vec2(aabb.mins.x, aabb.mins.y),
+                max: vec2(aabb.maxs.x, aabb.maxs.y),
+            };
+            draw_rect(&mut gizmos, plane, origin, &pushbox, Color::YELLOW);
+        }
+    }
+}
+
+fn draw_rect(gizmos: &mut Gizmos, plane: Option<CombatPlane>, origin: Vec2, rect: &Rectbox, color: Color) {
+    let position = origin + (rect.min + rect.max) / 2.;
+    let size = rect.max - rect.min;
+    match plane {
+        Some(plane) => gizmos.rect(plane.to_world(position), Quat::IDENTITY, plane.to_world_size(size), color),
+        None => gizmos.rect_2d(position, 0., size, color),
+    }
+}
@@ -0,0 +1,445 @@
+//! Inspector 中的帧数据编辑器.
+//! 在精灵图上逐帧查看动作, 拖拽受击/攻击/防御框 (拖拽框内移动, 拖拽右上角缩放) 和角色原点(锚点),
+//! 修改帧阶段和动作参数, 并保存回角色数据文件.
+
+use bevy::math::vec2;
+use bevy::prelude::*;
+use bevy_egui::EguiUserTextures;
+use egui::{Color32, Pos2, Sense, Stroke};
+use crate::{Action, ActionStage, Character, Frame, Rectbox};
+use crate::loading::{CharacterAssets, Characters, CharactersTextureAtlas};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum BoxKind {
+    Hurt,
+    Hit,
+    Block,
+}
+
+impl BoxKind {
+    fn color(&self) -> Color32 {
+        match self {
+            BoxKind::Hurt => Color32::BLUE,
+            BoxKind::Hit => Color32::RED,
+            BoxKind::Block => Color32::WHITE,
+        }
+    }
+
+    fn rectbox<'a>(&self, frame: &'a mut Frame) -> Option<&'a mut Rectbox> {
+        match self {
+            BoxKind::Hurt => Some(&mut frame.hurtbox),
+            BoxKind::Hit => frame.hitbox.as_mut(),
+            BoxKind::Block => frame.blockbox.as_mut(),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Drag {
+    /// 拖拽角色原点, 修改帧锚点
+    Anchor,
+    Box {
+        kind: BoxKind,
+        resize: bool,
+    },
+}
+
+pub(super) struct FrameEditor {
+    character: Option<String>,
+    action: Option<String>,
+    frame: usize,
+    zoom: f32,
+    drag: Option<Drag>,
+    status: String,
+}
+
+impl Default for FrameEditor {
+    fn default() -> Self {
+        Self {
+            character: None,
+            action: None,
+            frame: 0,
+            zoom: 2.,
+            drag: None,
+            status: String::new(),
+        }
+    }
+}
+
+impl FrameEditor {
+    pub(super) fn ui(&mut self, ui: &mut egui::Ui, world: &mut World) {
+        if !world.contains_resource::<Characters>() {
+            ui.label("角色数据未加载");
+            return;
+        }
+        world.resource_scope::<Characters, _>(|world, mut characters| {
+            self.select_ui(ui, &characters);
+
+            let (Some(character_name), Some(action_name)) = (self.character.clone(), self.action.clone()) else {
+                return;
+            };
+            let Some(character) = characters.get_mut(&character_name) else {
+                return;
+            };
+            let mut action_names: Vec<String> = character.actions.keys().cloned().collect();
+            action_names.sort();
+
+            ui.separator();
+            ui.horizontal_top(|ui| {
+                let Some(action) = character.actions.get_mut(&action_name) else {
+                    return;
+                };
+                if action.frames.is_empty() {
+                    ui.label("动作没有帧");
+                    return;
+                }
+                self.frame = self.frame.min(action.frames.len() - 1);
+                let (index, before) = (self.frame, action.frames[self.frame].clone());
+
+                let tile_size = ui.vertical(|ui| self.canvas_ui(ui, world, &character_name, &action_name, action)).inner;
+                ui.vertical(|ui| {
+                    self.frame_ui(ui, action, tile_size);
+                    ui.separator();
+                    action_ui(ui, action, &action_names);
+                });
+
+                // 编辑过的补齐帧要保存
+                if let Some(frame) = action.frames.get_mut(index).filter(|frame| **frame != before) {
+                    frame.padding = false;
+                }
+            });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("保存").clicked() {
+                    self.status = match save_character(world, character) {
+                        Ok(path) => format!("已保存 {}", path),
+                        Err(err) => format!("保存失败: {}", err),
+                    };
+                }
+                ui.label(&self.status);
+            });
+        });
+    }
+
+    fn select_ui(&mut self, ui: &mut egui::Ui, characters: &Characters) {
+        let mut character_names: Vec<&String> = characters.keys().collect();
+        character_names.sort();
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_label("角色")
+                .selected_text(self.character.clone().unwrap_or_default())
+                .show_ui(ui, |ui| {
+                    for name in character_names {
+                        if ui.selectable_label(self.character.as_ref() == Some(name), name).clicked() {
+                            self.character = Some(name.clone());
+                            self.action = None;
+                            self.frame = 0;
+                        }
+                    }
+                });
+
+            let Some(character) = self.character.as_ref().and_then(|name| characters.get(name)) else {
+                return;
+            };
+            let mut action_names: Vec<&String> = character.actions.keys().collect();
+            action_names.sort();
+            egui::ComboBox::from_label("动作")
+                .selected_text(self.action.clone().unwrap_or_default())
+                .show_ui(ui, |ui| {
+                    for name in action_names {
+                        if ui.selectable_label(self.action.as_ref() == Some(name), name).clicked() {
+                            self.action = Some(name.clone());
+                            self.frame = 0;
+                        }
+                    }
+                });
+            ui.add(egui::Slider::new(&mut self.zoom, 0.5..=6.0).text("缩放"));
+        });
+    }
+
+    /// 在精灵图当前帧上绘制并拖拽判定框, 返回当前帧尺寸
+    fn canvas_ui(&mut self, ui: &mut egui::Ui, world: &mut World, character_name: &str, action_name: &str, action: &mut Action) -> Vec2 {
+        let atlas = world
+            .resource::<CharactersTextureAtlas>()
+            .atlases
+            .get(character_name)
+            .and_then(|atlases| atlases.get(action_name))
+            .and_then(|handle| world.resource::<Assets<TextureAtlas>>().get(handle))
+            .map(|atlas| (atlas.texture.clone(), atlas.size, atlas.textures.get(self.frame).copied()));
+
+        let tile_size = atlas
+            .as_ref()
+            .and_then(|(_, _, rect)| rect.map(|rect| rect.size()))
+            .or(action.sprite.tile_size())
+            .unwrap_or(Vec2::splat(128.));
+        let scale = self.zoom;
+        let (response, painter) = ui.allocate_painter(egui::vec2(tile_size.x * scale, tile_size.y * scale), Sense::click_and_drag());
+        let canvas = response.rect;
+
+        painter.rect_filled(canvas, 0., Color32::from_gray(32));
+        if let Some((texture, size, Some(rect))) = atlas {
+            let texture_id = world.resource_mut::<EguiUserTextures>().add_image(texture);
+            let uv = egui::Rect::from_min_max(
+                Pos2::new(rect.min.x / size.x, rect.min.y / size.y),
+                Pos2::new(rect.max.x / size.x, rect.max.y / size.y),
+            );
+            painter.image(texture_id, canvas, uv, Color32::WHITE);
+        }
+
+        // 角色原点: 帧锚点, 未设置时为帧中心
+        let origin = match action.frames[self.frame].origin() {
+            Some(anchor) => canvas.min + egui::vec2(anchor.x * scale, anchor.y * scale),
+            None => canvas.center(),
+        };
+        let to_screen = |p: Vec2| Pos2::new(origin.x + p.x * scale, origin.y - p.y * scale);
+        let screen_rect = |rectbox: &Rectbox| egui::Rect::from_two_pos(to_screen(rectbox.min), to_screen(rectbox.max));
+
+        let frame = &mut action.frames[self.frame];
+        if response.drag_started() {
+            self.drag = response.interact_pointer_pos().and_then(|pos| {
+                if (pos - origin).length() < 6. {
+                    return Some(Drag::Anchor);
+                }
+                [BoxKind::Hit, BoxKind::Block, BoxKind::Hurt].into_iter().find_map(|kind| {
+                    let rect = screen_rect(kind.rectbox(frame)?);
+                    if !rect.expand(4.).contains(pos) {
+                        return None;
+                    }
+                    let resize = (pos - rect.right_top()).length() < 8.;
+                    Some(Drag::Box { kind, resize })
+                })
+            });
+        }
+        if response.dragged() {
+            let delta = response.drag_delta() / scale;
+            match self.drag {
+                Some(Drag::Anchor) => {
+                    // 锚点是图片像素坐标, y 轴向下
+                    let anchor = frame.anchor.get_or_insert(frame.default_anchor.unwrap_or(tile_size / 2.));
+                    *anchor += vec2(delta.x, delta.y);
+                }
+                Some(Drag::Box { kind, resize }) => {
+                    let delta = vec2(delta.x, -delta.y);
+                    if let Some(rectbox) = kind.rectbox(frame) {
+                        rectbox.max += delta;
+                        if resize {
+                            rectbox.max = rectbox.max.max(rectbox.min + 1.);
+                        } else {
+                            rectbox.min += delta;
+                        }
+                    }
+                }
+                None => {}
+            }
+        }
+        if response.drag_released() {
+            self.drag = None;
+        }
+
+        for kind in [BoxKind::Hurt, BoxKind::Block, BoxKind::Hit] {
+            if let Some(rectbox) = kind.rectbox(frame) {
+                let rect = screen_rect(rectbox);
+                painter.rect_stroke(rect, 0., Stroke::new(1.5, kind.color()));
+                painter.circle_filled(rect.right_top(), 3., kind.color());
+            }
+        }
+        painter.circle_stroke(origin, 3., Stroke::new(1., Color32::YELLOW));
+        tile_size
+    }
+
+    fn frame_ui(&mut self, ui: &mut egui::Ui, action: &mut Action, tile_size: Vec2) {
+        let last = action.frames.len() - 1;
+        ui.horizontal(|ui| {
+            if ui.button("<").clicked() {
+                self.frame = if self.frame == 0 { last } else { self.frame - 1 };
+            }
+            ui.add(egui::Slider::new(&mut self.frame, 0..=last).text("帧"));
+            if ui.button(">").clicked() {
+                self.frame = if self.frame == last { 0 } else { self.frame + 1 };
+            }
+        });
+
+        let frame = &mut action.frames[self.frame];
+        egui::ComboBox::from_label("阶段")
+            .selected_text(format!("{:?}", frame.stage))
+            .show_ui(ui, |ui| {
+                for stage in [ActionStage::Startup, ActionStage::Active, ActionStage::Recovery] {
+                    ui.selectable_value(&mut frame.stage, stage, format!("{:?}", stage));
+                }
+            });
+
+        ui.horizontal(|ui| {
+            let mut enabled = frame.anchor.is_some();
+            if ui.checkbox(&mut enabled, "锚点").changed() {
+                frame.anchor = enabled.then_some(frame.default_anchor.unwrap_or(tile_size / 2.));
+            }
+            if let Some(anchor) = frame.anchor.as_mut() {
+                vec2_ui(ui, anchor);
+            }
+        });
+        optional_path_ui(ui, "音效", &mut frame.sound);
+        rectbox_ui(ui, "受击框", &mut frame.hurtbox);
+        let hurtbox = frame.hurtbox;
+        optional_rectbox_ui(ui, "攻击框", &mut frame.hitbox, hurtbox);
+        optional_rectbox_ui(ui, "防御框", &mut frame.blockbox, hurtbox);
+    }
+}
+
+fn action_ui(ui: &mut egui::Ui, action: &mut Action, action_names: &[String]) {
+    egui::Grid::new("frame_editor_action").num_columns(2).show(ui, |ui| {
+        ui.label("时长");
+        ui.add(egui::DragValue::new(&mut action.duration).speed(0.01).clamp_range(0.01..=10.0).suffix("s"));
+        ui.end_row();
+
+        ui.label("循环");
+        ui.checkbox(&mut action.repeat, "");
+        ui.end_row();
+
+        ui.label("连招");
+        action_name_ui(ui, "frame_editor_next_action", &mut action.next_action, action_names);
+        ui.end_row();
+
+        ui.label("受击动作");
+        action_name_ui(ui, "frame_editor_hit_action", &mut action.hit_action, action_names);
+        ui.end_row();
+
+        ui.label("自身冲量");
+        optional_vec2_ui(ui, &mut action.internal_impulse);
+        ui.end_row();
+
+        ui.label("命中冲量");
+        optional_vec2_ui(ui, &mut action.external_impulse);
+        ui.end_row();
+
+        ui.label("伤害");
+        ui.add(egui::DragValue::new(&mut action.damage).clamp_range(0.0..=f32::MAX));
+        ui.end_row();
+
+        ui.label("命中得气");
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut action.meter_gain).prefix("自身: "));
+            ui.add(egui::DragValue::new(&mut action.victim_meter_gain).prefix("对方: "));
+        });
+        ui.end_row();
+
+        ui.label("消耗气");
+        ui.add(egui::DragValue::new(&mut action.meter_cost).clamp_range(0.0..=f32::MAX));
+        ui.end_row();
+
+        ui.label("EX 动作");
+        action_name_ui(ui, "frame_editor_ex_action", &mut action.ex_action, action_names);
+        ui.end_row();
+
+        ui.label("定格");
+        ui.add(egui::DragValue::new(&mut action.super_freeze).speed(0.01).clamp_range(0.0..=5.0).suffix("s"));
+        ui.end_row();
+
+        ui.label("命中音效");
+        optional_path_ui(ui, "", &mut action.hit_sound);
+        ui.end_row();
+
+        ui.label("防御音效");
+        optional_path_ui(ui, "", &mut action.block_sound);
+        ui.end_row();
+    });
+}
+
+/// 可选的资源路径, 如音效
+fn optional_path_ui(ui: &mut egui::Ui, label: &str, value: &mut Option<String>) {
+    ui.horizontal(|ui| {
+        let mut enabled = value.is_some();
+        if ui.checkbox(&mut enabled, label).changed() {
+            *value = enabled.then(String::new);
+        }
+        if let Some(value) = value {
+            ui.text_edit_singleline(value);
+        }
+    });
+}
+
+fn action_name_ui(ui: &mut egui::Ui, id: &str, value: &mut Option<String>, action_names: &[String]) {
+    egui::ComboBox::from_id_source(id)
+        .selected_text(value.clone().unwrap_or_else(|| "-".to_string()))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(value, None, "-");
+            for name in action_names {
+                ui.selectable_value(value, Some(name.clone()), name);
+            }
+        });
+}
+
+fn vec2_ui(ui: &mut egui::Ui, value: &mut Vec2) {
+    ui.add(egui::DragValue::new(&mut value.x).prefix("x: "));
+    ui.add(egui::DragValue::new(&mut value.y).prefix("y: "));
+}
+
+fn optional_vec2_ui(ui: &mut egui::Ui, value: &mut Option<Vec2>) {
+    ui.horizontal(|ui| {
+        let mut enabled = value.is_some();
+        if ui.checkbox(&mut enabled, "").changed() {
+            *value = if enabled { Some(Vec2::ZERO) } else { None };
+        }
+        if let Some(value) = value {
+            vec2_ui(ui, value);
+        }
+    });
+}
+
+fn rectbox_ui(ui: &mut egui::Ui, label: &str, rectbox: &mut Rectbox) {
+    ui.horizontal(|ui| {
+        ui.label(label);
+        vec2_ui(ui, &mut rectbox.min);
+        vec2_ui(ui, &mut rectbox.max);
+    });
+}
+
+/// 勾选时以受击框为初始值添加判定框
+fn optional_rectbox_ui(ui: &mut egui::Ui, label: &str, rectbox: &mut Option<Rectbox>, initial: Rectbox) {
+    ui.horizontal(|ui| {
+        let mut enabled = rectbox.is_some();
+        if ui.checkbox(&mut enabled, label).changed() {
+            *rectbox = if enabled { Some(initial) } else { None };
+        }
+        if let Some(rectbox) = rectbox {
+            vec2_ui(ui, &mut rectbox.min);
+            vec2_ui(ui, &mut rectbox.max);
+        }
+    });
+}
+
+/// 写回加载该角色的 `.character.ron` 文件
+#[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+fn save_character(world: &World, character: &Character) -> Result<String, String> {
+    let assets = world.resource::<Assets<Character>>();
+    let handle = world
+        .resource::<CharacterAssets>()
+        .characters
+        .iter()
+        .find(|handle| assets.get(*handle).is_some_and(|loaded| loaded.name == character.name))
+        .ok_or_else(|| format!("找不到角色文件: {}", character.name))?;
+    let asset_path = world
+        .resource::<AssetServer>()
+        .get_handle_path(handle)
+        .ok_or_else(|| format!("找不到角色文件: {}", character.name))?;
+
+    let path = bevy::asset::FileAssetIo::get_base_path()
+        .join("assets")
+        .join(asset_path.path());
+    // 加载时补齐的帧不写回文件
+    let mut character = character.clone();
+    for action in character.actions.values_mut() {
+        while action.frames.last().is_some_and(|frame| frame.padding) {
+            action.frames.pop();
+        }
+    }
+    let content = ron::ser::to_string_pretty(&character, ron::ser::PrettyConfig::default())
+        .map_err(|err| err.to_string())?;
+    std::fs::write(&path, content).map_err(|err| err.to_string())?;
+    Ok(path.display().to_string())
+}
+
+#[cfg(any(target_arch = "wasm32", target_os = "android"))]
+fn save_character(_world: &World, _character: &Character) -> Result<String, String> {
+    Err("当前平台不支持保存".to_string())
+}
@@ -18,7 +18,11 @@ use bevy_egui::EguiSet;
 use bevy_inspector_egui::__macro_exports::bevy_reflect::TypeRegistry;
 use egui_dock::{DockArea, NodeIndex, Style, Tree};
 use egui_gizmo::{Gizmo, GizmoMode, GizmoOrientation};
-use crate::{GameState, MainCamera};
+use crate::{GameState, GameViewport, MainCamera};
+use crate::tools::BoundingBoxes;
+use super::frame_editor::FrameEditor;
+use super::shader_errors::{self, ShaderErrorsPlugin};
+use super::animation_panel;
 
 pub struct InspectPlugin;
 
@@ -27,7 +31,9 @@ impl Plugin for InspectPlugin {
         app
             .add_plugins(DefaultInspectorConfigPlugin)
             .add_plugins(bevy_egui::EguiPlugin)
+            .add_plugins(ShaderErrorsPlugin)
             .insert_resource(UiState::new())
+            .init_resource::<GameViewport>()
             .add_systems(
                 PostUpdate,
                 show_ui_system
@@ -56,7 +62,15 @@ fn show_ui_system(world: &mut World) {
     let mut egui_context = egui_context.clone();
 
     world.resource_scope::<UiState, _>(|world, mut ui_state| {
-        ui_state.ui(world, egui_context.get_mut())
+        ui_state.ui(world, egui_context.get_mut());
+
+        // 只显示选中实体的包围盒时使用
+        let selected: Vec<Entity> = ui_state.selected_entities.iter().collect();
+        if let Some(mut boxes) = world.get_resource_mut::<BoundingBoxes>() {
+            if boxes.selected != selected {
+                boxes.selected = selected;
+            }
+        }
     });
 }
 
@@ -65,6 +79,7 @@ fn set_camera_viewport(
     ui_state: Res<UiState>,
     primary_window: Query<&mut Window, With<PrimaryWindow>>,
     egui_settings: Res<bevy_egui::EguiSettings>,
+    mut game_viewport: ResMut<GameViewport>,
     mut cameras: Query<&mut Camera, With<MainCamera>>,
 ) {
     let mut cam = cameras.single_mut();
@@ -83,6 +98,16 @@ fn set_camera_viewport(
         physical_size: UVec2::new(viewport_size.x as u32, viewport_size.y as u32),
         depth: 0.0..1.0,
     });
+
+    // the HUD is laid out in logical pixels
+    let egui_scale = egui_settings.scale_factor as f32;
+    let logical_rect = Rect::from_corners(
+        Vec2::new(ui_state.viewport_rect.min.x, ui_state.viewport_rect.min.y) * egui_scale,
+        Vec2::new(ui_state.viewport_rect.max.x, ui_state.viewport_rect.max.y) * egui_scale,
+    );
+    if **game_viewport != Some(logical_rect) {
+        **game_viewport = Some(logical_rect);
+    }
 }
 
 fn set_gizmo_mode(input: Res<Input<KeyCode>>, mut ui_state: ResMut<UiState>) {
@@ -111,6 +136,7 @@ struct UiState {
     selected_entities: SelectedEntities,
     selection: InspectorSelection,
     gizmo_mode: GizmoMode,
+    frame_editor: FrameEditor,
 }
 
 impl UiState {
@@ -120,7 +146,7 @@ impl UiState {
             tree.split_right(NodeIndex::root(), 0.75, vec![EguiWindow::Inspector]);
         let [game, _hierarchy] = tree.split_left(game, 0.2, vec![EguiWindow::Hierarchy]);
         let [_game, _bottom] =
-            tree.split_below(game, 0.8, vec![EguiWindow::Resources, EguiWindow::Assets]);
+            tree.split_below(game, 0.8, vec![EguiWindow::Resources, EguiWindow::Assets, EguiWindow::FrameData, EguiWindow::Shaders, EguiWindow::Animation]);
 
         Self {
             tree,
@@ -128,6 +154,7 @@ impl UiState {
             selection: InspectorSelection::Entities,
             viewport_rect: egui::Rect::NOTHING,
             gizmo_mode: GizmoMode::Translate,
+            frame_editor: FrameEditor::default(),
         }
     }
 
@@ -138,6 +165,7 @@ impl UiState {
             selected_entities: &mut self.selected_entities,
             selection: &mut self.selection,
             gizmo_mode: self.gizmo_mode,
+            frame_editor: &mut self.frame_editor,
         };
         DockArea::new(&mut self.tree)
             .style(Style::from_egui(ctx.style().as_ref()))
@@ -152,6 +180,9 @@ enum EguiWindow {
     Resources,
     Assets,
     Inspector,
+    FrameData,
+    Shaders,
+    Animation,
 }
 
 struct TabViewer<'a> {
@@ -160,6 +191,7 @@ struct TabViewer<'a> {
     selection: &'a mut InspectorSelection,
     viewport_rect: &'a mut egui::Rect,
     gizmo_mode: GizmoMode,
+    frame_editor: &'a mut FrameEditor,
 }
 
 impl egui_dock::TabViewer for TabViewer<'_> {
@@ -183,6 +215,9 @@ impl egui_dock::TabViewer for TabViewer<'_> {
             }
             EguiWindow::Resources => select_resource(ui, &type_registry, self.selection),
             EguiWindow::Assets => select_asset(ui, &type_registry, self.world, self.selection),
+            EguiWindow::FrameData => self.frame_editor.ui(ui, self.world),
+            EguiWindow::Shaders => shader_errors::ui(ui, self.world),
+            EguiWindow::Animation => animation_panel::ui(ui, self.world),
             EguiWindow::Inspector => match *self.selection {
                 InspectorSelection::Entities => match self.selected_entities.as_slice() {
                     &[entity] => ui_for_entity_with_children(self.world, entity, ui),
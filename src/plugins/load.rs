@@ -1,4 +1,5 @@
-use crate::{MyAssets, GameState};
+use crate::{MyAssets, GameState, Character, Stage};
+use crate::loading::{build_characters, load_character_sprites, CharacterAssets, CharacterLoader, PendingCharacterSprites, StageAssets, StageLoader};
 use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
 
@@ -6,15 +7,26 @@ pub struct LoadPlugin;
 
 impl Plugin for LoadPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Loading), setup)
+        app.add_asset::<Character>()
+            .init_asset_loader::<CharacterLoader>()
+            .add_asset::<Stage>()
+            .init_asset_loader::<StageLoader>()
+            .add_systems(OnEnter(GameState::Loading), setup)
             .add_loading_state(
                 LoadingState::new(GameState::Loading).continue_to_state(GameState::Init)
             )
             .add_collection_to_loading_state::<_, MyAssets>(GameState::Loading)
+            .add_collection_to_loading_state::<_, CharacterAssets>(GameState::Loading)
+            .add_collection_to_loading_state::<_, StageAssets>(GameState::Loading)
+            .add_systems(OnEnter(GameState::Init), load_character_sprites)
+            .add_systems(
+                Update,
+                build_characters.run_if(in_state(GameState::Init).and_then(resource_exists::<PendingCharacterSprites>())),
+            )
         ;
     }
 }
 
 fn setup(
     mut commands: Commands,
-) {}
\ No newline at end of file
+) {}
@@ -1,7 +1,14 @@
 mod load;
 mod inspect;
 mod game;
+mod frame_editor;
+mod shader_errors;
+mod animation_panel;
+#[cfg(debug_assertions)]
+mod debug;
 
 pub use load::LoadPlugin;
 pub use inspect::InspectPlugin;
 pub use game::GamePlugin;
+#[cfg(debug_assertions)]
+pub use debug::{DebugOverlay, DebugOverlayPlugin};
@@ -0,0 +1,214 @@
+//! Inspector 中的着色器错误面板.
+//! 运行时修改 `assets/shaders` 下的着色器, 渲染管线编译失败时在面板中显示文件、行号和错误信息,
+//! 并把着色器恢复为上一次编译成功的版本, 画面保持不变; 修好后保存即可重新加载.
+
+use std::sync::{Arc, Mutex};
+use bevy::asset::HandleId;
+use bevy::prelude::*;
+use bevy::render::extract_resource::{ExtractResource, ExtractResourcePlugin};
+use bevy::render::render_resource::{CachedPipelineState, PipelineCache, PipelineCacheError, PipelineDescriptor};
+use bevy::render::{Render, RenderApp, RenderSet};
+use bevy::utils::{HashMap, HashSet};
+use egui::{Color32, RichText};
+
+pub struct ShaderErrorsPlugin;
+
+impl Plugin for ShaderErrorsPlugin {
+    fn build(&self, app: &mut App) {
+        let report = PipelineReportSlot::default();
+        app.init_resource::<ShaderErrors>()
+            .init_resource::<WatchedShaders>()
+            .insert_resource(report.clone())
+            .add_plugins(ExtractResourcePlugin::<WatchedShaders>::default())
+            .add_systems(Update, track_shaders);
+
+        app.sub_app_mut(RenderApp)
+            .insert_resource(report)
+            .add_systems(Render, report_pipelines.in_set(RenderSet::Cleanup));
+    }
+}
+
+/// 显示在面板中的错误
+pub struct ShaderError {
+    pub file: String,
+    /// 编译器信息中的行号, 找不到时为空
+    pub line: Option<u32>,
+    pub message: String,
+}
+
+#[derive(Resource, Default)]
+pub struct ShaderErrors {
+    pub errors: Vec<ShaderError>,
+    /// 出错后已恢复为上一次编译成功的版本
+    pub restored: bool,
+    /// 编译成功的版本
+    good: HashMap<HandleId, Shader>,
+    /// 修改后还没有编译结果的版本
+    candidates: HashMap<HandleId, Shader>,
+    /// 正在恢复的着色器, 它们的修改事件不算作新的修改
+    restoring: HashSet<HandleId>,
+}
+
+/// `shaders/` 下的着色器; 每次修改 `generation` 加一, 只采用之后的编译结果
+#[derive(Resource, ExtractResource, Clone, Default)]
+struct WatchedShaders {
+    generation: u64,
+    handles: HashSet<HandleId>,
+}
+
+#[derive(Default, Clone)]
+struct PipelineReport {
+    generation: u64,
+    /// 还有管线在等待编译
+    pending: bool,
+    errors: Vec<(Vec<HandleId>, String)>,
+}
+
+/// 渲染世界写入编译结果, 主世界读取
+#[derive(Resource, Clone, Default)]
+struct PipelineReportSlot(Arc<Mutex<PipelineReport>>);
+
+/// 收集使用这些着色器的管线的编译状态
+fn report_pipelines(
+    pipeline_cache: Res<PipelineCache>,
+    watched: Res<WatchedShaders>,
+    slot: Res<PipelineReportSlot>,
+) {
+    let mut report = PipelineReport {
+        generation: watched.generation,
+        ..default()
+    };
+    for pipeline in pipeline_cache.pipelines() {
+        let PipelineDescriptor::RenderPipelineDescriptor(descriptor) = &pipeline.descriptor else {
+            continue;
+        };
+        let mut shaders = vec![descriptor.vertex.shader.id()];
+        if let Some(fragment) = &descriptor.fragment {
+            shaders.push(fragment.shader.id());
+        }
+        if !shaders.iter().any(|id| watched.handles.contains(id)) {
+            continue;
+        }
+        match &pipeline.state {
+            CachedPipelineState::Ok(_) => {}
+            CachedPipelineState::Err(PipelineCacheError::ProcessShaderError(error)) => {
+                report.errors.push((shaders, error.to_string()));
+            }
+            CachedPipelineState::Err(PipelineCacheError::CreateShaderModule(error)) => {
+                report.errors.push((shaders, error.clone()));
+            }
+            // 等待着色器或导入的模块加载
+            _ => report.pending = true,
+        }
+    }
+    *slot.0.lock().unwrap() = report;
+}
+
+/// 记录修改的着色器; 编译失败时显示错误并恢复, 成功时记为新的可用版本
+fn track_shaders(
+    mut events: EventReader<AssetEvent<Shader>>,
+    mut shaders: ResMut<Assets<Shader>>,
+    asset_server: Res<AssetServer>,
+    slot: Res<PipelineReportSlot>,
+    mut watched: ResMut<WatchedShaders>,
+    mut state: ResMut<ShaderErrors>,
+) {
+    let state = state.as_mut();
+    for event in events.iter() {
+        let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = event else {
+            continue;
+        };
+        let id = handle.id();
+        let is_custom = asset_server
+            .get_handle_path(id)
+            .is_some_and(|path| path.path().starts_with("shaders"));
+        if !is_custom {
+            continue;
+        }
+        watched.handles.insert(id);
+        if state.restoring.remove(&id) {
+            continue;
+        }
+        if let Some(shader) = shaders.get(handle) {
+            state.candidates.insert(id, shader.clone());
+            watched.generation += 1;
+        }
+    }
+
+    if state.candidates.is_empty() {
+        return;
+    }
+    let report = slot.0.lock().unwrap().clone();
+    if report.generation < watched.generation || report.pending {
+        return;
+    }
+
+    if report.errors.is_empty() {
+        state.good.extend(state.candidates.drain());
+        state.errors.clear();
+        state.restored = false;
+        return;
+    }
+
+    // 错误可能出在被导入的模块中, 文件显示为刚修改的着色器
+    let path = |id: &HandleId| {
+        asset_server
+            .get_handle_path(*id)
+            .map(|path| path.path().display().to_string())
+            .unwrap_or_else(|| format!("{id:?}"))
+    };
+    let files = state.candidates.keys().map(path).collect::<Vec<_>>().join(", ");
+    let mut messages = HashSet::new();
+    state.errors = report
+        .errors
+        .into_iter()
+        .filter(|(_, message)| messages.insert(message.clone()))
+        .map(|(_, message)| ShaderError {
+            file: files.clone(),
+            line: error_line(&message),
+            message,
+        })
+        .collect();
+
+    state.restored = false;
+    for (id, _) in std::mem::take(&mut state.candidates) {
+        if let Some(good) = state.good.get(&id) {
+            state.restoring.insert(id);
+            shaders.set_untracked(id, good.clone());
+            state.restored = true;
+        }
+    }
+    for error in &state.errors {
+        warn!("shader error in {}: {}", error.file, error.message);
+    }
+}
+
+/// 从 `xxx.wgsl:行:列` 形式的位置中取出行号
+fn error_line(message: &str) -> Option<u32> {
+    message.split("wgsl:").skip(1).find_map(|rest| {
+        let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+        digits.parse().ok()
+    })
+}
+
+pub fn ui(ui: &mut egui::Ui, world: &mut World) {
+    let state = world.resource::<ShaderErrors>();
+    if state.errors.is_empty() {
+        ui.label("着色器没有错误");
+        return;
+    }
+    if state.restored {
+        ui.label("已恢复为上一次编译成功的版本, 修改后保存重新加载");
+    }
+    egui::ScrollArea::vertical().show(ui, |ui| {
+        for error in &state.errors {
+            ui.separator();
+            let location = match error.line {
+                Some(line) => format!("{}  第 {} 行", error.file, line),
+                None => error.file.clone(),
+            };
+            ui.label(RichText::new(location).color(Color32::LIGHT_RED).strong());
+            ui.label(RichText::new(&error.message).monospace());
+        }
+    });
+}
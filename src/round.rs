@@ -0,0 +1,126 @@
+//! 回合规则: 每回合 99 秒, 一方被 KO 或时间耗尽时剩余生命比例高的一方赢下回合, 先赢两回合的玩家获胜.
+//! 回合结束后显示结果 `ROUND_END_SECONDS` 秒, 然后双方回满生命、回到场地的开场位置; 整场比赛结束后清空胜场重新开始.
+
+use std::collections::HashMap;
+use bevy::ecs::event::ManualEventReader;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use crate::{CombatEvent, GameState, Health, UID};
+use crate::meter::SuperFreeze;
+use crate::stage::CurrentStage;
+
+/// 每回合时长(秒)
+pub const ROUND_SECONDS: f32 = 99.;
+/// 赢得比赛需要的回合数
+pub const ROUNDS_TO_WIN: u32 = 2;
+/// 回合结束到重置的时间(秒), 期间显示回合结果
+pub const ROUND_END_SECONDS: f32 = 2.;
+
+pub struct RoundPlugin;
+
+impl Plugin for RoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Round>()
+            .add_systems(OnEnter(GameState::Playing), reset_match)
+            .add_systems(Update, (tick_round, end_round).chain().run_if(in_state(GameState::Playing)));
+    }
+}
+
+#[derive(Resource)]
+pub struct Round {
+    /// 当前回合, 从 1 开始
+    pub number: u32,
+    pub timer: Timer,
+    /// 每个玩家赢下的回合数
+    pub wins: HashMap<UID, u32>,
+    /// 本回合已经结束, 计时结束后重置
+    over: Option<Timer>,
+}
+
+impl Default for Round {
+    fn default() -> Self {
+        Self {
+            number: 1,
+            timer: Timer::from_seconds(ROUND_SECONDS, TimerMode::Once),
+            wins: HashMap::new(),
+            over: None,
+        }
+    }
+}
+
+impl Round {
+    pub fn wins(&self, uid: UID) -> u32 {
+        self.wins.get(&uid).copied().unwrap_or_default()
+    }
+
+    /// 剩余秒数, 向上取整
+    pub fn seconds_left(&self) -> u32 {
+        self.timer.remaining_secs().ceil() as u32
+    }
+}
+
+fn reset_match(mut round: ResMut<Round>) {
+    *round = Round::default();
+}
+
+fn tick_round(time: Res<Time>, freeze: Res<SuperFreeze>, mut round: ResMut<Round>) {
+    if freeze.active() || round.over.is_some() {
+        return;
+    }
+    round.timer.tick(time.delta());
+}
+
+fn end_round(
+    time: Res<Time>,
+    mut round: ResMut<Round>,
+    stage: Res<CurrentStage>,
+    // 同一个系统既读取 KO 又发送 RoundOver, 用 `Events` 和手动的读取器避免读写冲突
+    mut combat_events: ResMut<Events<CombatEvent>>,
+    mut reader: Local<ManualEventReader<CombatEvent>>,
+    mut fighters: Query<(&UID, &mut Health, &mut Transform, &mut Velocity)>,
+) {
+    let ko = reader.iter(&combat_events).any(|event| matches!(event, CombatEvent::KO(_)));
+
+    // 显示完回合结果再重置, 期间的 KO 不再计算
+    if let Some(outro) = round.over.as_mut() {
+        if !outro.tick(time.delta()).finished() {
+            return;
+        }
+        let number = round.number + 1;
+        if round.wins.values().any(|wins| *wins >= ROUNDS_TO_WIN) {
+            *round = Round::default();
+        } else {
+            round.number = number;
+            round.timer.reset();
+            round.over = None;
+        }
+        for (uid, mut health, mut transform, mut velocity) in fighters.iter_mut() {
+            health.current = health.max;
+            transform.translation = stage.spawn_point(*uid);
+            *velocity = Velocity::zero();
+        }
+        return;
+    }
+
+    let timeout = round.timer.finished();
+    if !ko && !timeout {
+        return;
+    }
+
+    // 剩余生命比例高的一方获胜, 相同时平局
+    let mut standings: Vec<(UID, f32)> = fighters.iter().map(|(uid, health, ..)| (*uid, health.fraction())).collect();
+    standings.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    let winner = match standings.as_slice() {
+        [(first, a), (_, b), ..] if a > b => Some(*first),
+        [(first, _)] => Some(*first),
+        _ => None,
+    };
+    if let Some(winner) = winner {
+        *round.wins.entry(winner).or_default() += 1;
+        info!("round {} winner: {:?}", round.number, winner);
+    } else {
+        info!("round {} draw", round.number);
+    }
+    round.over = Some(Timer::from_seconds(ROUND_END_SECONDS, TimerMode::Once));
+    combat_events.send(CombatEvent::RoundOver { winner, timeout: !ko });
+}
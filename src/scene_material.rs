@@ -0,0 +1,117 @@
+//! glTF 场景的材质替换: 场景实例生成网格时, 按场地数据的 `materials` 替换或修改材质.
+//! 美术在 Blender 中给物体加自定义属性 `material = "xxx"`(导出为 glTF extras), 场地数据中用 `tag: Some("xxx")` 声明对应的材质;
+//! 也可以直接按节点或网格的名字匹配.
+
+use bevy::gltf::GltfExtras;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use crate::{CustomMaterial, GameState, MaterialOverride, SceneMaterial};
+use crate::bend::BendBuffer;
+use crate::stage::CurrentStage;
+
+pub struct SceneMaterialPlugin;
+
+impl Plugin for SceneMaterialPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, apply_overrides.run_if(in_state(GameState::Playing)));
+    }
+}
+
+/// glTF extras 中指定材质的属性名
+const MATERIAL_PROPERTY: &str = "material";
+
+/// 网格实体和它的父节点(glTF 节点)中, 任意一个匹配即可
+fn matches(rule: &MaterialOverride, names: &[&str], tags: &[String]) -> bool {
+    rule.tag.as_ref().is_some_and(|tag| tags.contains(tag))
+        || rule.names.iter().any(|name| names.contains(&name.as_str()))
+}
+
+fn material_tag(extras: &GltfExtras) -> Option<String> {
+    let value: serde_json::Value = match serde_json::from_str(&extras.value) {
+        Ok(value) => value,
+        Err(err) => {
+            warn!("invalid glTF extras {}: {err}", extras.value);
+            return None;
+        }
+    };
+    value.get(MATERIAL_PROPERTY)?.as_str().map(str::to_string)
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn apply_overrides(
+    mut commands: Commands,
+    stage: Res<CurrentStage>,
+    // 同一条规则生成的 `CustomMaterial` 共用
+    mut foliage: Local<HashMap<usize, Handle<CustomMaterial>>>,
+    spawned: Query<(Entity, Option<&Name>, Option<&GltfExtras>, Option<&Parent>, &Handle<StandardMaterial>), Added<Handle<StandardMaterial>>>,
+    nodes: Query<(Option<&Name>, Option<&GltfExtras>)>,
+    mut standard_materials: ResMut<Assets<StandardMaterial>>,
+    mut custom_materials: ResMut<Assets<CustomMaterial>>,
+    bend_buffer: Res<BendBuffer>,
+) {
+    if stage.is_changed() {
+        foliage.clear();
+    }
+    if stage.materials.is_empty() {
+        return;
+    }
+
+    for (entity, name, extras, parent, material_handle) in spawned.iter() {
+        let node = parent.and_then(|parent| nodes.get(parent.get()).ok());
+        let names: Vec<&str> = name
+            .into_iter()
+            .chain(node.and_then(|(name, _)| name))
+            .map(Name::as_str)
+            .collect();
+        let tags: Vec<String> = extras
+            .into_iter()
+            .chain(node.and_then(|(_, extras)| extras))
+            .filter_map(material_tag)
+            .collect();
+        let Some((index, rule)) = stage.materials.iter().enumerate().find(|(_, rule)| matches(rule, &names, &tags)) else {
+            continue;
+        };
+
+        match &rule.material {
+            SceneMaterial::Foliage { color, tip_color, height_range, roughness, translucency } => {
+                let handle = foliage.entry(index).or_insert_with(|| {
+                    let default = CustomMaterial::new(&bend_buffer);
+                    custom_materials.add(CustomMaterial {
+                        color: *color,
+                        tip_color: *tip_color,
+                        height_range: height_range.unwrap_or(default.height_range),
+                        roughness: roughness.unwrap_or(default.roughness),
+                        translucency: translucency.unwrap_or(default.translucency),
+                        ..default
+                    })
+                });
+                commands
+                    .entity(entity)
+                    .remove::<Handle<StandardMaterial>>()
+                    .insert(handle.clone());
+            }
+            SceneMaterial::Standard { base_color, emissive, perceptual_roughness, metallic, unlit } => {
+                // glTF 的材质可能被多个网格共用, 复制一份再修改
+                let Some(mut material) = standard_materials.get(material_handle).cloned() else {
+                    continue;
+                };
+                if let Some(base_color) = base_color {
+                    material.base_color = *base_color;
+                }
+                if let Some(emissive) = emissive {
+                    material.emissive = *emissive;
+                }
+                if let Some(perceptual_roughness) = perceptual_roughness {
+                    material.perceptual_roughness = *perceptual_roughness;
+                }
+                if let Some(metallic) = metallic {
+                    material.metallic = *metallic;
+                }
+                if let Some(unlit) = unlit {
+                    material.unlit = *unlit;
+                }
+                commands.entity(entity).insert(standard_materials.add(material));
+            }
+        }
+    }
+}
@@ -0,0 +1,414 @@
+//! 选人界面: 加载完成后、选场地之前, 每个玩家选择角色和调色板.
+//! - 1P: A/D 选角色, W/S 换颜色, J 确认, K 取消; 或第一个手柄的十字键 / South / East
+//! - 2P: ←/→ 选角色, ↑/↓ 换颜色, 小键盘 1 确认, 小键盘 2 取消; 或第二个手柄
+//!
+//! 预览和对战使用同样的换色图集(见 `CharactersTextureAtlas::get_action`).
+
+use std::collections::HashMap;
+use bevy::prelude::*;
+use crate::{AnimationTimer, GameState, MyAssets, UID};
+use crate::audio::settings_closed;
+use crate::loading::{Characters, CharactersTextureAtlas};
+
+pub struct SelectPlugin;
+
+impl Plugin for SelectPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Selections>()
+            .add_systems(OnEnter(GameState::CharacterSelect), setup)
+            .add_systems(
+                Update,
+                (select_input.run_if(settings_closed), update_cards, update_previews, animate_previews)
+                    .chain()
+                    .run_if(in_state(GameState::CharacterSelect)),
+            )
+            .add_systems(OnExit(GameState::CharacterSelect), cleanup);
+    }
+}
+
+/// 玩家选择的角色和调色板
+#[derive(Clone, Debug)]
+pub struct PlayerSelection {
+    pub character: String,
+    pub palette: usize,
+}
+
+/// 每个玩家的选择, 进入对战时按此生成角色
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct Selections(pub HashMap<UID, PlayerSelection>);
+
+impl Selections {
+    /// 同角色对战时, 后面的玩家如果和前面的玩家配色相同, 依次换成下一个未被使用的调色板
+    pub fn resolve_mirror_palettes(&mut self, characters: &Characters) {
+        let mut uids: Vec<UID> = self.keys().copied().collect();
+        uids.sort_by_key(|uid| uid.0);
+        let mut used: Vec<(String, usize)> = Vec::new();
+        for uid in uids {
+            let selection = self.get_mut(&uid).unwrap();
+            let palettes = characters.get(&selection.character).map_or(0, |character| character.palettes.len());
+            for _ in 0..palettes {
+                if !used.contains(&(selection.character.clone(), selection.palette)) {
+                    break;
+                }
+                selection.palette = (selection.palette + 1) % palettes;
+            }
+            used.push((selection.character.clone(), selection.palette));
+        }
+    }
+}
+
+#[derive(Component)]
+struct SelectScreen;
+
+#[derive(Component)]
+struct CharacterCard(usize);
+
+/// 玩家的选人光标
+#[derive(Component)]
+struct PlayerPanel {
+    uid: UID,
+    cursor: usize,
+    palette: usize,
+    ready: bool,
+}
+
+#[derive(Component)]
+struct PlayerLabel(UID);
+
+/// 玩家当前选中角色的 idle 动画预览
+#[derive(Component)]
+struct IdlePreview(UID);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SelectInput {
+    Prev,
+    Next,
+    PalettePrev,
+    PaletteNext,
+    Confirm,
+    Cancel,
+}
+
+pub(crate) const PLAYER_COLORS: [Color; 2] = [Color::rgb(0.9, 0.2, 0.2), Color::rgb(0.2, 0.4, 0.9)];
+
+/// 选人界面中的角色顺序
+fn character_names(characters: &Characters) -> Vec<String> {
+    let mut names: Vec<String> = characters.keys().cloned().collect();
+    names.sort();
+    names
+}
+
+fn setup(
+    mut commands: Commands,
+    my_assets: Res<MyAssets>,
+    asset_server: Res<AssetServer>,
+    characters: Res<Characters>,
+    characters_texture_atlas: Res<CharactersTextureAtlas>,
+) {
+    let text_style = |font_size: f32| TextStyle {
+        font: my_assets.font.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+    let names = character_names(&characters);
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::SpaceEvenly,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.8).into(),
+                ..default()
+            },
+            SelectScreen,
+        ))
+        .with_children(|root| {
+            root.spawn(TextBundle::from_section("选择角色", text_style(48.)));
+
+            // 角色卡片: 头像 + 名字
+            root.spawn(NodeBundle {
+                style: Style {
+                    column_gap: Val::Px(16.),
+                    ..default()
+                },
+                ..default()
+            }).with_children(|row| {
+                for (index, name) in names.iter().enumerate() {
+                    let character = &characters[name];
+                    row.spawn((
+                        NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Column,
+                                align_items: AlignItems::Center,
+                                padding: UiRect::all(Val::Px(6.)),
+                                ..default()
+                            },
+                            background_color: Color::DARK_GRAY.into(),
+                            ..default()
+                        },
+                        CharacterCard(index),
+                    )).with_children(|card| {
+                        let portrait_style = Style {
+                            width: Val::Px(96.),
+                            height: Val::Px(96.),
+                            ..default()
+                        };
+                        match &character.portrait {
+                            Some(portrait) => {
+                                card.spawn(ImageBundle {
+                                    style: portrait_style,
+                                    image: UiImage::new(asset_server.load(portrait.as_str())),
+                                    ..default()
+                                });
+                            }
+                            None => {
+                                let idle = characters_texture_atlas.atlases.get(name).and_then(|atlases| atlases.get("idle"));
+                                if let Some(texture_atlas) = idle {
+                                    card.spawn(AtlasImageBundle {
+                                        style: portrait_style,
+                                        texture_atlas: texture_atlas.clone(),
+                                        texture_atlas_image: UiTextureAtlasImage::default(),
+                                        ..default()
+                                    });
+                                }
+                            }
+                        }
+                        card.spawn(TextBundle::from_section(name.clone(), text_style(20.)));
+                    });
+                }
+            });
+
+            // 玩家面板: idle 动画预览 + 选择状态
+            root.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Percent(80.),
+                    justify_content: JustifyContent::SpaceBetween,
+                    ..default()
+                },
+                ..default()
+            }).with_children(|row| {
+                for (i, uid) in [UID(1), UID(2)].into_iter().enumerate() {
+                    row.spawn((
+                        NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Column,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        PlayerPanel { uid, cursor: i.min(names.len().saturating_sub(1)), palette: 0, ready: false },
+                    )).with_children(|panel| {
+                        panel.spawn((
+                            AtlasImageBundle {
+                                style: Style {
+                                    width: Val::Px(200.),
+                                    height: Val::Px(200.),
+                                    ..default()
+                                },
+                                texture_atlas_image: UiTextureAtlasImage {
+                                    // 2P 面向 1P
+                                    flip_x: i == 1,
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            IdlePreview(uid),
+                            AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
+                        ));
+                        panel.spawn((TextBundle::from_section("", TextStyle {
+                            color: PLAYER_COLORS[i],
+                            ..text_style(24.)
+                        }), PlayerLabel(uid)));
+                    });
+                }
+            });
+        });
+}
+
+pub(crate) fn read_input(
+    uid: UID,
+    keys: &Input<KeyCode>,
+    gamepads: &Gamepads,
+    buttons: &Input<GamepadButton>,
+) -> Option<SelectInput> {
+    let key_map = match uid.0 {
+        1 => [KeyCode::A, KeyCode::D, KeyCode::S, KeyCode::W, KeyCode::J, KeyCode::K],
+        _ => [KeyCode::Left, KeyCode::Right, KeyCode::Down, KeyCode::Up, KeyCode::Numpad1, KeyCode::Numpad2],
+    };
+    let button_map = [
+        GamepadButtonType::DPadLeft,
+        GamepadButtonType::DPadRight,
+        GamepadButtonType::DPadDown,
+        GamepadButtonType::DPadUp,
+        GamepadButtonType::South,
+        GamepadButtonType::East,
+    ];
+    let inputs = [
+        SelectInput::Prev,
+        SelectInput::Next,
+        SelectInput::PalettePrev,
+        SelectInput::PaletteNext,
+        SelectInput::Confirm,
+        SelectInput::Cancel,
+    ];
+    // 第 n 个手柄控制第 n 个玩家
+    let gamepad = gamepads.iter().nth(uid.0 as usize - 1);
+    inputs.into_iter().enumerate().find_map(|(i, input)| {
+        let pressed = keys.just_pressed(key_map[i])
+            || gamepad.is_some_and(|gamepad| buttons.just_pressed(GamepadButton::new(gamepad, button_map[i])));
+        pressed.then_some(input)
+    })
+}
+
+fn select_input(
+    keys: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    characters: Res<Characters>,
+    mut selections: ResMut<Selections>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut panels: Query<&mut PlayerPanel>,
+) {
+    let names = character_names(&characters);
+    if names.is_empty() {
+        return;
+    }
+
+    for mut panel in panels.iter_mut() {
+        let Some(input) = read_input(panel.uid, &keys, &gamepads, &buttons) else {
+            continue;
+        };
+        let palettes = characters[&names[panel.cursor]].palettes.len().max(1);
+        match (input, panel.ready) {
+            (SelectInput::Prev, false) => {
+                panel.cursor = (panel.cursor + names.len() - 1) % names.len();
+                panel.palette = 0;
+            }
+            (SelectInput::Next, false) => {
+                panel.cursor = (panel.cursor + 1) % names.len();
+                panel.palette = 0;
+            }
+            (SelectInput::PalettePrev, false) => panel.palette = (panel.palette + palettes - 1) % palettes,
+            (SelectInput::PaletteNext, false) => panel.palette = (panel.palette + 1) % palettes,
+            (SelectInput::Confirm, false) => panel.ready = true,
+            (SelectInput::Cancel, true) => panel.ready = false,
+            _ => {}
+        }
+    }
+
+    if panels.iter().all(|panel| panel.ready) {
+        selections.clear();
+        for panel in panels.iter() {
+            selections.insert(panel.uid, PlayerSelection {
+                character: names[panel.cursor].clone(),
+                palette: panel.palette,
+            });
+        }
+        selections.resolve_mirror_palettes(&characters);
+        game_state.set(GameState::StageSelect);
+    }
+}
+
+fn update_cards(
+    characters: Res<Characters>,
+    panels: Query<&PlayerPanel, Changed<PlayerPanel>>,
+    all_panels: Query<&PlayerPanel>,
+    mut cards: Query<(&CharacterCard, &mut BackgroundColor)>,
+    mut labels: Query<(&PlayerLabel, &mut Text)>,
+) {
+    if panels.is_empty() {
+        return;
+    }
+    let names = character_names(&characters);
+
+    // 卡片底色为选中它的玩家颜色, 两个玩家都选中时混合
+    for (card, mut background) in cards.iter_mut() {
+        let selected: Vec<Color> = all_panels
+            .iter()
+            .filter(|panel| panel.cursor == card.0)
+            .map(|panel| PLAYER_COLORS[panel.uid.0 as usize - 1])
+            .collect();
+        *background = match selected.as_slice() {
+            [] => Color::DARK_GRAY,
+            [color] => *color,
+            [a, b, ..] => Color::rgb((a.r() + b.r()) / 2., (a.g() + b.g()) / 2., (a.b() + b.b()) / 2.),
+        }.into();
+    }
+
+    for (label, mut text) in labels.iter_mut() {
+        let Some(panel) = all_panels.iter().find(|panel| panel.uid == label.0) else {
+            continue;
+        };
+        let Some(name) = names.get(panel.cursor) else {
+            continue;
+        };
+        text.sections[0].value = format!(
+            "{}P {}  颜色 {}{}",
+            panel.uid.0,
+            name,
+            panel.palette + 1,
+            if panel.ready { "  准备" } else { "" },
+        );
+    }
+}
+
+fn update_previews(
+    characters: Res<Characters>,
+    characters_texture_atlas: Res<CharactersTextureAtlas>,
+    panels: Query<&PlayerPanel, Changed<PlayerPanel>>,
+    mut previews: Query<(&IdlePreview, &mut Handle<TextureAtlas>, &mut UiTextureAtlasImage, &mut AnimationTimer)>,
+) {
+    let names = character_names(&characters);
+    for panel in panels.iter() {
+        let Some(name) = names.get(panel.cursor) else {
+            continue;
+        };
+        let (Some(action), Some(texture_atlas)) = (
+            characters[name].actions.get("idle"),
+            characters_texture_atlas.get_action(name, panel.palette, "idle"),
+        ) else {
+            continue;
+        };
+        for (preview, mut handle, mut image, mut timer) in previews.iter_mut() {
+            if preview.0 != panel.uid || *handle == *texture_atlas {
+                continue;
+            }
+            *handle = texture_atlas.clone();
+            image.index = 0;
+            *timer = AnimationTimer(Timer::from_seconds(
+                action.duration / action.frames.len().max(1) as f32,
+                TimerMode::Repeating,
+            ));
+        }
+    }
+}
+
+fn animate_previews(
+    time: Res<Time>,
+    texture_atlases: Res<Assets<TextureAtlas>>,
+    mut previews: Query<(&Handle<TextureAtlas>, &mut UiTextureAtlasImage, &mut AnimationTimer), With<IdlePreview>>,
+) {
+    for (handle, mut image, mut timer) in previews.iter_mut() {
+        timer.tick(time.delta());
+        if !timer.just_finished() {
+            continue;
+        }
+        let Some(texture_atlas) = texture_atlases.get(handle) else {
+            continue;
+        };
+        image.index = (image.index + 1) % texture_atlas.len().max(1);
+    }
+}
+
+fn cleanup(mut commands: Commands, query: Query<Entity, With<SelectScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,389 @@
+//! 场地: 选人之后选择场地, 进入对战时按场地数据生成地面、墙壁、glTF 场景、装饰、灯光和背景音乐, 离开对战时清理.
+//! - 选场地: A/D 或 ←/→ 切换, J / 小键盘 1 确认, K / 小键盘 2 返回选人; 手柄同选人界面
+
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use bevy_sprite3d::{Sprite3d, Sprite3dParams};
+use crate::{CustomMaterial, Decoration, GameState, MyAssets, SceneBounds, Stage, UID, Wall};
+use crate::audio::{settings_closed, Music};
+use crate::bend::BendBuffer;
+use crate::billboard::CombatPlane;
+use crate::loading::StageAssets;
+use crate::select::{read_input, SelectInput, PLAYER_COLORS};
+use crate::tools::{parse_scene, SceneCommand, SceneHandle};
+
+pub struct StagePlugin;
+
+impl Plugin for StagePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Stages>()
+            .init_resource::<CurrentStage>()
+            .add_systems(OnEnter(GameState::Init), collect_stages)
+            .add_systems(OnEnter(GameState::StageSelect), setup_select)
+            .add_systems(Update, (select_input.run_if(settings_closed), update_cards).chain().run_if(in_state(GameState::StageSelect)))
+            .add_systems(OnExit(GameState::StageSelect), cleanup_select)
+            .add_systems(OnEnter(GameState::Playing), spawn_stage)
+            .add_systems(Update, (measure_decorations, spawn_decorations).chain().run_if(in_state(GameState::Playing)))
+            .add_systems(OnExit(GameState::Playing), despawn_stage);
+    }
+}
+
+/// 墙壁和地面碰撞体的尺寸(像素)
+const WALL_HALF_WIDTH: f32 = 10.;
+const WALL_HALF_HEIGHT: f32 = 1000.;
+const FLOOR_HALF_HEIGHT: f32 = 20.;
+
+/// 所有场地, 按名字排序
+#[derive(Resource, Deref, DerefMut, Default)]
+pub struct Stages(pub Vec<Stage>);
+
+/// 本场对战的场地, 在选场地界面确认时设置
+#[derive(Resource, Deref, DerefMut, Default)]
+pub struct CurrentStage(pub Stage);
+
+/// 随场地生成的实体, 离开对战时销毁
+#[derive(Component)]
+pub struct StageEntity;
+
+/// 图片加载完成后生成的装饰公告板
+#[derive(Component)]
+struct PendingDecoration {
+    decoration: Decoration,
+    image: Handle<Image>,
+    /// 图片尺寸(像素), 加载完成前为空
+    size: Option<Vec2>,
+}
+
+#[derive(Component)]
+struct StageSelectScreen {
+    cursor: usize,
+}
+
+#[derive(Component)]
+struct StageCard(usize);
+
+fn collect_stages(mut stages: ResMut<Stages>, stage_assets: Res<StageAssets>, assets: Res<Assets<Stage>>) {
+    stages.0 = stage_assets.stages.iter().filter_map(|handle| assets.get(handle)).cloned().collect();
+    stages.sort_by(|a, b| a.name.cmp(&b.name));
+    for stage in stages.iter() {
+        info!("stage loaded: {}", stage.name);
+    }
+}
+
+fn setup_select(
+    mut commands: Commands,
+    my_assets: Res<MyAssets>,
+    asset_server: Res<AssetServer>,
+    stages: Res<Stages>,
+) {
+    let text_style = |font_size: f32| TextStyle {
+        font: my_assets.font.clone(),
+        font_size,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::SpaceEvenly,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.8).into(),
+                ..default()
+            },
+            StageSelectScreen { cursor: 0 },
+        ))
+        .with_children(|root| {
+            root.spawn(TextBundle::from_section("选择场地", text_style(48.)));
+
+            // 场地卡片: 预览图 + 名字
+            root.spawn(NodeBundle {
+                style: Style {
+                    column_gap: Val::Px(16.),
+                    ..default()
+                },
+                ..default()
+            }).with_children(|row| {
+                for (index, stage) in stages.iter().enumerate() {
+                    row.spawn((
+                        NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Column,
+                                align_items: AlignItems::Center,
+                                padding: UiRect::all(Val::Px(6.)),
+                                ..default()
+                            },
+                            background_color: Color::DARK_GRAY.into(),
+                            ..default()
+                        },
+                        StageCard(index),
+                    )).with_children(|card| {
+                        if let Some(preview) = &stage.preview {
+                            card.spawn(ImageBundle {
+                                style: Style {
+                                    width: Val::Px(256.),
+                                    height: Val::Px(144.),
+                                    ..default()
+                                },
+                                image: UiImage::new(asset_server.load(preview.as_str())),
+                                ..default()
+                            });
+                        }
+                        card.spawn(TextBundle::from_section(stage.name.clone(), text_style(24.)));
+                    });
+                }
+            });
+
+            root.spawn(TextBundle::from_section("J 确认  K 返回选人", text_style(20.)));
+        });
+}
+
+fn select_input(
+    mut commands: Commands,
+    keys: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    stages: Res<Stages>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut screens: Query<&mut StageSelectScreen>,
+) {
+    let Ok(mut screen) = screens.get_single_mut() else {
+        return;
+    };
+    // 两个玩家都可以操作
+    for uid in [UID(1), UID(2)] {
+        let Some(input) = read_input(uid, &keys, &gamepads, &buttons) else {
+            continue;
+        };
+        let count = stages.len().max(1);
+        match input {
+            SelectInput::Prev => screen.cursor = (screen.cursor + count - 1) % count,
+            SelectInput::Next => screen.cursor = (screen.cursor + 1) % count,
+            SelectInput::Confirm => {
+                // 没有场地数据时使用空场地
+                let stage = stages.get(screen.cursor).cloned().unwrap_or_default();
+                commands.insert_resource(CurrentStage(stage));
+                game_state.set(GameState::Playing);
+                return;
+            }
+            SelectInput::Cancel => {
+                game_state.set(GameState::CharacterSelect);
+                return;
+            }
+            SelectInput::PalettePrev | SelectInput::PaletteNext => {}
+        }
+    }
+}
+
+fn update_cards(
+    screens: Query<&StageSelectScreen, Changed<StageSelectScreen>>,
+    mut cards: Query<(&StageCard, &mut BackgroundColor)>,
+) {
+    let Ok(screen) = screens.get_single() else {
+        return;
+    };
+    for (card, mut background) in cards.iter_mut() {
+        *background = if card.0 == screen.cursor { PLAYER_COLORS[0] } else { Color::DARK_GRAY }.into();
+    }
+}
+
+fn cleanup_select(mut commands: Commands, query: Query<Entity, With<StageSelectScreen>>) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn spawn_stage(
+    mut commands: Commands,
+    stage: Res<CurrentStage>,
+    asset_server: Res<AssetServer>,
+    mut music: ResMut<Music>,
+    mut plane: ResMut<CombatPlane>,
+    mut ambient_light: ResMut<AmbientLight>,
+    mut scene_commands: EventWriter<SceneCommand>,
+) {
+    let (left, right) = stage.walls;
+
+    commands.spawn((
+        Collider::cuboid((right - left) / 2. + WALL_HALF_WIDTH * 2., FLOOR_HALF_HEIGHT),
+        TransformBundle::from(Transform::from_xyz((left + right) / 2., stage.floor - FLOOR_HALF_HEIGHT, 0.)),
+        Restitution {
+            coefficient: stage.restitution.unwrap_or(0.),
+            combine_rule: CoefficientCombineRule::Min,
+        },
+        Friction {
+            coefficient: stage.friction.unwrap_or(2.),
+            combine_rule: CoefficientCombineRule::Max,
+        },
+        StageEntity,
+        Name::new("Floor"),
+    ));
+
+    // 墙壁底部与地面底部对齐
+    let wall_y = stage.floor - FLOOR_HALF_HEIGHT * 2. + WALL_HALF_HEIGHT;
+    for x in [left - WALL_HALF_WIDTH, right + WALL_HALF_WIDTH] {
+        commands.spawn((
+            Collider::cuboid(WALL_HALF_WIDTH, WALL_HALF_HEIGHT),
+            TransformBundle::from(Transform::from_xyz(x, wall_y, 0.)),
+            Wall,
+            StageEntity,
+            Name::new("Wall"),
+        ));
+    }
+
+    // 地面顶部对齐到 3D 场景的 y = 0
+    plane.origin.y = -stage.floor / plane.pixels_per_metre;
+
+    if let Some(scene) = &stage.scene {
+        let (file_path, scene_index) = parse_scene(scene.clone());
+        commands.insert_resource(SceneHandle::new(asset_server.load(file_path), scene_index));
+    }
+
+    // 在 `despawn_stage` 中卸载
+    for (index, prop) in stage.props.iter().enumerate() {
+        let rotation = Quat::from_euler(
+            EulerRot::YXZ,
+            prop.rotation.y.to_radians(),
+            prop.rotation.x.to_radians(),
+            prop.rotation.z.to_radians(),
+        );
+        scene_commands.send(SceneCommand::Load {
+            key: format!("prop {index}"),
+            path: prop.scene.clone(),
+            transform: Transform::from_translation(prop.position)
+                .with_rotation(rotation)
+                .with_scale(prop.scale),
+        });
+    }
+
+    for decoration in &stage.decorations {
+        commands.spawn((
+            PendingDecoration {
+                decoration: decoration.clone(),
+                image: asset_server.load(decoration.image.as_str()),
+                size: None,
+            },
+            StageEntity,
+        ));
+    }
+
+    if let Some(sun) = &stage.lighting.sun {
+        commands.spawn((
+            DirectionalLightBundle {
+                directional_light: DirectionalLight {
+                    color: sun.color,
+                    illuminance: sun.illuminance,
+                    shadows_enabled: sun.shadows,
+                    ..default()
+                },
+                transform: Transform::from_rotation(Quat::from_euler(
+                    EulerRot::YXZ,
+                    sun.rotation.y.to_radians(),
+                    sun.rotation.x.to_radians(),
+                    0.,
+                )),
+                ..default()
+            },
+            StageEntity,
+            Name::new("Sun"),
+        ));
+    }
+    if let Some(ambient) = &stage.lighting.ambient {
+        ambient_light.color = ambient.color;
+        ambient_light.brightness = ambient.brightness;
+    }
+
+    **music = stage.music.clone();
+}
+
+/// `Sprite3d` 需要图片尺寸, 等图片加载完成再生成公告板
+fn measure_decorations(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    images: Res<Assets<Image>>,
+    mut pending: Query<(Entity, &mut PendingDecoration)>,
+) {
+    for (entity, mut pending) in pending.iter_mut() {
+        if pending.size.is_some() {
+            continue;
+        }
+        if asset_server.get_load_state(&pending.image) == LoadState::Failed {
+            warn!("failed to load stage decoration {}", pending.decoration.image);
+            commands.entity(entity).despawn();
+            continue;
+        }
+        if let Some(image) = images.get(&pending.image) {
+            pending.size = Some(image.size());
+        }
+    }
+}
+
+fn spawn_decorations(
+    mut commands: Commands,
+    mut sprite_params: Sprite3dParams,
+    mut custom_materials: ResMut<Assets<CustomMaterial>>,
+    bend_buffer: Res<BendBuffer>,
+    pending: Query<(Entity, &PendingDecoration)>,
+) {
+    for (entity, pending) in pending.iter() {
+        let Some(size) = pending.size else {
+            continue;
+        };
+        let decoration = &pending.decoration;
+        let mut entity = commands.entity(entity);
+        entity
+            .remove::<PendingDecoration>()
+            .insert((
+                Sprite3d {
+                    image: pending.image.clone(),
+                    pixels_per_metre: decoration.pixels_per_metre,
+                    unlit: true,
+                    transform: Transform::from_translation(decoration.position).with_scale(decoration.scale),
+                    ..default()
+                }.bundle(&mut sprite_params),
+                Name::new("Decoration"),
+            ));
+        if let Some(foliage) = &decoration.foliage {
+            // 公告板以中心为原点, 高度为图片高度换算成米
+            let half_height = size.y / decoration.pixels_per_metre / 2.;
+            entity
+                .remove::<Handle<StandardMaterial>>()
+                .insert(custom_materials.add(CustomMaterial {
+                    color: foliage.color,
+                    tip_color: foliage.tip_color,
+                    height_range: Vec2::new(-half_height, half_height),
+                    texture: Some(pending.image.clone()),
+                    ..CustomMaterial::new(&bend_buffer)
+                }));
+        }
+    }
+}
+
+fn despawn_stage(
+    mut commands: Commands,
+    scene_handle: Option<Res<SceneHandle>>,
+    mut scene_spawner: ResMut<SceneSpawner>,
+    mut music: ResMut<Music>,
+    mut ambient_light: ResMut<AmbientLight>,
+    mut scene_commands: EventWriter<SceneCommand>,
+    query: Query<Entity, With<StageEntity>>,
+) {
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    if let Some(instance_id) = scene_handle.and_then(|scene_handle| scene_handle.instance_id()) {
+        scene_spawner.despawn_instance(instance_id);
+    }
+    // 场地摆放的 glTF 道具(`Stage::props`)一起卸载
+    scene_commands.send(SceneCommand::UnloadAll);
+    commands.remove_resource::<SceneHandle>();
+    commands.remove_resource::<SceneBounds>();
+    *ambient_light = AmbientLight::default();
+    **music = None;
+}
@@ -2,4 +2,4 @@ mod scene_viewer_plugin;
 mod camera_controller_plugin;
 
 pub use scene_viewer_plugin::*;
-pub use camera_controller_plugin::CameraController;
+pub use camera_controller_plugin::{CameraController, CameraControllerPlugin};
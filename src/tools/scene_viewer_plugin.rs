@@ -2,15 +2,18 @@
 //! To use in your own application:
 //! - Copy the code for the `SceneViewerPlugin` and add the plugin to your App.
 //! - Insert an initialized `SceneHandle` resource into your App's `AssetServer`.
+//!   Inserting a new `SceneHandle` swaps the scene and despawns the old instance.
+//! - Send `SceneCommand`s to load extra scenes (props from other glTF files) at given transforms,
+//!   and to unload them again. Progress and errors are reported as `SceneEvent`s.
 
 use bevy::{
     asset::LoadState, gltf::Gltf, input::common_conditions::input_just_pressed, prelude::*,
-    scene::InstanceId,
+    asset::HandleId, gizmos::AabbGizmo, render::primitives::Aabb, scene::InstanceId, utils::HashMap,
 };
 
 use std::f32::consts::*;
 use std::fmt;
-use crate::GameState;
+use crate::{GameState, SceneBounds};
 use crate::tools::CameraController;
 
 #[derive(Resource)]
@@ -32,24 +35,69 @@ impl SceneHandle {
             has_light: false,
         }
     }
+
+    /// The spawned scene instance, once the glTF has loaded.
+    pub fn instance_id(&self) -> Option<InstanceId> {
+        self.instance_id
+    }
 }
 
-#[cfg(not(feature = "animation"))]
-const INSTRUCTIONS: &str = r#"
-Scene Controls:
-    L           - animate light direction
-    U           - toggle shadows
-    C           - cycle through the camera controller and any cameras loaded from the scene
+/// The key `SceneEvent`s use for the scene in `SceneHandle`.
+pub const MAIN_SCENE: &str = "main";
+
+/// Requests to load or unload additional scenes, identified by a key.
+#[derive(Event, Clone, Debug)]
+pub enum SceneCommand {
+    /// Load `path` (optionally ending in `#SceneN`) as a child of a root entity at `transform`.
+    /// A scene already loaded under the same key is despawned first.
+    Load {
+        key: String,
+        path: String,
+        transform: Transform,
+    },
+    Unload { key: String },
+    /// Unload every scene loaded through `SceneCommand::Load`.
+    UnloadAll,
+}
+
+#[derive(Event, Clone, Debug)]
+pub enum SceneEvent {
+    /// The scene's entities have been spawned.
+    Loaded { key: String },
+    /// The glTF failed to load or doesn't contain the requested scene.
+    Error { key: String, message: String },
+}
 
-    compile with "--features animation" for animation controls.
-"#;
+/// A scene loaded through `SceneCommand::Load`.
+pub struct LoadedScene {
+    pub gltf_handle: Handle<Gltf>,
+    scene_index: usize,
+    /// Parent of the scene's entities, carrying the requested transform.
+    pub root: Entity,
+    instance_id: Option<InstanceId>,
+    pub is_loaded: bool,
+    failed: bool,
+}
+
+/// Scenes loaded in addition to the one in `SceneHandle`, by key.
+#[derive(Resource, Default)]
+pub struct LoadedScenes(HashMap<String, LoadedScene>);
+
+impl LoadedScenes {
+    pub fn get(&self, key: &str) -> Option<&LoadedScene> {
+        self.0.get(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &LoadedScene)> {
+        self.0.iter()
+    }
+}
 
-#[cfg(feature = "animation")]
 const INSTRUCTIONS: &str = "
 Scene Controls:
-    L           - animate light direction
-    U           - toggle shadows
-    B           - toggle bounding boxes
+    L           - animate light direction (free camera only, F9)
+    U           - toggle shadows (free camera only, F9)
+    B           - cycle bounding boxes: all (with the scene bounds) / entities selected in the inspector / off
     C           - cycle through the camera controller and any cameras loaded from the scene
 
     Space       - Play/Pause animation
@@ -67,20 +115,151 @@ pub struct SceneViewerPlugin;
 impl Plugin for SceneViewerPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<CameraTracker>()
-            .add_systems(PreUpdate, scene_load_check.run_if(in_state(GameState::Playing)))
+            .init_resource::<LoadedScenes>()
+            .init_resource::<SceneAnimations>()
+            .init_resource::<BoundingBoxes>()
+            .add_event::<SceneCommand>()
+            .add_event::<SceneEvent>()
+            .add_systems(PreUpdate, scene_load_check.run_if(in_state(GameState::Playing).and_then(resource_exists::<SceneHandle>())))
+            .add_systems(PreUpdate, (handle_scene_commands, loaded_scenes_check).chain())
             .add_systems(
                 Update,
                 (
                     update_lights.run_if(in_state(GameState::Playing)),
                     camera_tracker.run_if(in_state(GameState::Playing)),
-                    toggle_bounding_boxes.run_if(in_state(GameState::Playing)),
+                    toggle_bounding_boxes.run_if(in_state(GameState::Playing).and_then(input_just_pressed(KeyCode::B))),
+                    (update_bounding_boxes, draw_scene_bounds).run_if(in_state(GameState::Playing)),
+                    (collect_animations, animation_controls, sync_animation_players)
+                        .chain()
+                        .run_if(in_state(GameState::Playing)),
                 ),
             );
     }
 }
 
-fn toggle_bounding_boxes(mut config: ResMut<GizmoConfig>) {
-    config.aabb.draw_all ^= true;
+/// Which bounding boxes are drawn.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum BoundingBoxMode {
+    #[default]
+    Off,
+    /// Every entity with an `Aabb`, plus the scene-wide bounds.
+    All,
+    /// Only the entities in `BoundingBoxes::selected` and their descendants.
+    Selected,
+}
+
+#[derive(Resource, Default)]
+pub struct BoundingBoxes {
+    pub mode: BoundingBoxMode,
+    /// Entities selected in the inspector hierarchy.
+    pub selected: Vec<Entity>,
+}
+
+/// Marks the `AabbGizmo`s inserted for `BoundingBoxMode::Selected`, so only those are removed again.
+#[derive(Component)]
+struct SelectedAabb;
+
+fn toggle_bounding_boxes(mut boxes: ResMut<BoundingBoxes>) {
+    boxes.mode = match boxes.mode {
+        BoundingBoxMode::Off => BoundingBoxMode::All,
+        BoundingBoxMode::All => BoundingBoxMode::Selected,
+        BoundingBoxMode::Selected => BoundingBoxMode::Off,
+    };
+    info!("Bounding boxes: {:?}", boxes.mode);
+}
+
+fn update_bounding_boxes(
+    mut commands: Commands,
+    boxes: Res<BoundingBoxes>,
+    mut config: ResMut<GizmoConfig>,
+    children: Query<&Children>,
+    with_aabb: Query<(), With<Aabb>>,
+    shown: Query<Entity, With<SelectedAabb>>,
+) {
+    if !boxes.is_changed() {
+        return;
+    }
+    config.aabb.draw_all = boxes.mode == BoundingBoxMode::All;
+
+    for entity in &shown {
+        commands.entity(entity).remove::<(AabbGizmo, SelectedAabb)>();
+    }
+    if boxes.mode != BoundingBoxMode::Selected {
+        return;
+    }
+    // A selected glTF node usually has no mesh itself, so its descendants' boxes are shown too.
+    for &selected in &boxes.selected {
+        let descendants = std::iter::once(selected).chain(children.iter_descendants(selected));
+        for entity in descendants.filter(|entity| with_aabb.contains(*entity)) {
+            commands.entity(entity).insert((AabbGizmo::default(), SelectedAabb));
+        }
+    }
+}
+
+fn draw_scene_bounds(boxes: Res<BoundingBoxes>, bounds: Option<Res<SceneBounds>>, mut gizmos: Gizmos) {
+    let Some(bounds) = bounds.filter(|_| boxes.mode == BoundingBoxMode::All) else {
+        return;
+    };
+    let transform = Transform::from_translation(bounds.center.into())
+        .with_scale(Vec3::from(bounds.half_extents) * 2.);
+    gizmos.cuboid(transform, Color::YELLOW);
+}
+
+enum SpawnProgress {
+    /// The glTF is still loading.
+    Waiting,
+    Spawned { instance_id: InstanceId, has_light: bool },
+    Failed(String),
+}
+
+/// Spawns scene `scene_index` of the glTF once it has loaded, using `spawn`.
+fn try_spawn(
+    gltf_handle: &Handle<Gltf>,
+    scene_index: usize,
+    asset_server: &AssetServer,
+    gltf_assets: &Assets<Gltf>,
+    scenes: &mut Assets<Scene>,
+    spawn: impl FnOnce(Handle<Scene>) -> InstanceId,
+) -> SpawnProgress {
+    let path = asset_server
+        .get_handle_path(gltf_handle)
+        .map(|path| path.path().display().to_string())
+        .unwrap_or_default();
+    match asset_server.get_load_state(gltf_handle) {
+        LoadState::Loaded => {}
+        LoadState::Failed => return SpawnProgress::Failed(format!("failed to load {path}")),
+        _ => return SpawnProgress::Waiting,
+    }
+    let Some(gltf) = gltf_assets.get(gltf_handle) else {
+        return SpawnProgress::Waiting;
+    };
+    if gltf.scenes.len() > 1 {
+        info!("Displaying scene {} out of {} of {path}", scene_index, gltf.scenes.len());
+        info!("You can select the scene by adding '#Scene' followed by a number to the end of the file path (e.g '#Scene1' to load the second scene).");
+    }
+    let Some(gltf_scene_handle) = gltf.scenes.get(scene_index) else {
+        return SpawnProgress::Failed(format!(
+            "{path} doesn't contain scene {scene_index} (it has {})",
+            gltf.scenes.len()
+        ));
+    };
+    let Some(scene) = scenes.get_mut(gltf_scene_handle) else {
+        return SpawnProgress::Waiting;
+    };
+
+    let mut query = scene
+        .world
+        .query::<(Option<&DirectionalLight>, Option<&PointLight>)>();
+    let has_light = query
+        .iter(&scene.world)
+        .any(|(maybe_directional_light, maybe_point_light)| {
+            maybe_directional_light.is_some() || maybe_point_light.is_some()
+        });
+
+    SpawnProgress::Spawned {
+        instance_id: spawn(gltf_scene_handle.clone_weak()),
+        has_light,
+    }
 }
 
 fn scene_load_check(
@@ -89,69 +268,309 @@ fn scene_load_check(
     gltf_assets: Res<Assets<Gltf>>,
     mut scene_handle: ResMut<SceneHandle>,
     mut scene_spawner: ResMut<SceneSpawner>,
+    mut previous_instance: Local<Option<InstanceId>>,
+    mut failed: Local<bool>,
+    mut events: EventWriter<SceneEvent>,
 ) {
+    // A new `SceneHandle` replaces the previous scene
+    if scene_handle.is_added() {
+        if let Some(instance_id) = previous_instance.take() {
+            scene_spawner.despawn_instance(instance_id);
+        }
+        *failed = false;
+    }
+
     match scene_handle.instance_id {
-        None => {
-            if asset_server.get_load_state(&scene_handle.gltf_handle) == LoadState::Loaded {
-                let gltf = gltf_assets.get(&scene_handle.gltf_handle).unwrap();
-                if gltf.scenes.len() > 1 {
-                    info!(
-                        "Displaying scene {} out of {}",
-                        scene_handle.scene_index,
-                        gltf.scenes.len()
-                    );
-                    info!("You can select the scene by adding '#Scene' followed by a number to the end of the file path (e.g '#Scene1' to load the second scene).");
+        None if !*failed => {
+            let progress = try_spawn(
+                &scene_handle.gltf_handle,
+                scene_handle.scene_index,
+                &asset_server,
+                &gltf_assets,
+                &mut scenes,
+                |handle| scene_spawner.spawn(handle),
+            );
+            match progress {
+                SpawnProgress::Waiting => {}
+                SpawnProgress::Spawned { instance_id, has_light } => {
+                    scene_handle.has_light = has_light;
+                    scene_handle.instance_id = Some(instance_id);
+                    *previous_instance = Some(instance_id);
+                    info!("Spawning scene...");
+                }
+                SpawnProgress::Failed(message) => {
+                    error!("{message}");
+                    *failed = true;
+                    events.send(SceneEvent::Error { key: MAIN_SCENE.to_string(), message });
                 }
-
-                let gltf_scene_handle =
-                    gltf.scenes
-                        .get(scene_handle.scene_index)
-                        .unwrap_or_else(|| {
-                            panic!(
-                                "glTF file doesn't contain scene {}!",
-                                scene_handle.scene_index
-                            )
-                        });
-                let scene = scenes.get_mut(gltf_scene_handle).unwrap();
-
-                let mut query = scene
-                    .world
-                    .query::<(Option<&DirectionalLight>, Option<&PointLight>)>();
-                scene_handle.has_light =
-                    query
-                        .iter(&scene.world)
-                        .any(|(maybe_directional_light, maybe_point_light)| {
-                            maybe_directional_light.is_some() || maybe_point_light.is_some()
-                        });
-
-                scene_handle.instance_id =
-                    Some(scene_spawner.spawn(gltf_scene_handle.clone_weak()));
-
-                info!("Spawning scene...");
             }
         }
         Some(instance_id) if !scene_handle.is_loaded => {
             if scene_spawner.instance_is_ready(instance_id) {
                 info!("...done!");
                 scene_handle.is_loaded = true;
+                events.send(SceneEvent::Loaded { key: MAIN_SCENE.to_string() });
             }
         }
-        Some(_) => {}
+        _ => {}
     }
 }
+
+fn unload(commands: &mut Commands, scene_spawner: &mut SceneSpawner, scene: LoadedScene) {
+    if let Some(instance_id) = scene.instance_id {
+        scene_spawner.despawn_instance(instance_id);
+    }
+    commands.entity(scene.root).despawn();
+}
+
+fn handle_scene_commands(
+    mut commands: Commands,
+    mut requests: EventReader<SceneCommand>,
+    mut loaded_scenes: ResMut<LoadedScenes>,
+    mut scene_spawner: ResMut<SceneSpawner>,
+    asset_server: Res<AssetServer>,
+) {
+    for request in requests.iter() {
+        match request {
+            SceneCommand::Load { key, path, transform } => {
+                if let Some(old) = loaded_scenes.0.remove(key) {
+                    unload(&mut commands, &mut scene_spawner, old);
+                }
+                let (file_path, scene_index) = parse_scene(path.clone());
+                let root = commands
+                    .spawn((SpatialBundle::from_transform(*transform), Name::new(format!("Scene {key}"))))
+                    .id();
+                loaded_scenes.0.insert(key.clone(), LoadedScene {
+                    gltf_handle: asset_server.load(file_path),
+                    scene_index,
+                    root,
+                    instance_id: None,
+                    is_loaded: false,
+                    failed: false,
+                });
+            }
+            SceneCommand::Unload { key } => {
+                if let Some(old) = loaded_scenes.0.remove(key) {
+                    unload(&mut commands, &mut scene_spawner, old);
+                }
+            }
+            SceneCommand::UnloadAll => {
+                for (_, old) in loaded_scenes.0.drain() {
+                    unload(&mut commands, &mut scene_spawner, old);
+                }
+            }
+        }
+    }
+}
+
+fn loaded_scenes_check(
+    asset_server: Res<AssetServer>,
+    mut scenes: ResMut<Assets<Scene>>,
+    gltf_assets: Res<Assets<Gltf>>,
+    mut loaded_scenes: ResMut<LoadedScenes>,
+    mut scene_spawner: ResMut<SceneSpawner>,
+    mut events: EventWriter<SceneEvent>,
+) {
+    for (key, scene) in loaded_scenes.0.iter_mut() {
+        match scene.instance_id {
+            None if !scene.failed => {
+                let root = scene.root;
+                let progress = try_spawn(
+                    &scene.gltf_handle,
+                    scene.scene_index,
+                    &asset_server,
+                    &gltf_assets,
+                    &mut scenes,
+                    |handle| scene_spawner.spawn_as_child(handle, root),
+                );
+                match progress {
+                    SpawnProgress::Waiting => {}
+                    SpawnProgress::Spawned { instance_id, .. } => scene.instance_id = Some(instance_id),
+                    SpawnProgress::Failed(message) => {
+                        error!("scene {key}: {message}");
+                        scene.failed = true;
+                        events.send(SceneEvent::Error { key: key.clone(), message });
+                    }
+                }
+            }
+            Some(instance_id) if !scene.is_loaded && scene_spawner.instance_is_ready(instance_id) => {
+                scene.is_loaded = true;
+                events.send(SceneEvent::Loaded { key: key.clone() });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The animations of the glTF in `SceneHandle`, played on every `AnimationPlayer` in its scene instance.
+#[derive(Resource)]
+pub struct SceneAnimations {
+    /// Clip names (from the glTF, or `Animation N` for unnamed ones) and handles.
+    pub clips: Vec<(String, Handle<AnimationClip>)>,
+    pub current: usize,
+    pub paused: bool,
+    pub looping: bool,
+    pub speed: f32,
+    /// Jump to this time (seconds) on the next update.
+    pub seek: Option<f32>,
+    gltf: Option<HandleId>,
+}
+
+impl Default for SceneAnimations {
+    fn default() -> Self {
+        Self {
+            clips: Vec::new(),
+            current: 0,
+            paused: false,
+            looping: true,
+            speed: 1.0,
+            seek: None,
+            gltf: None,
+        }
+    }
+}
+
+impl SceneAnimations {
+    pub fn current_clip(&self) -> Option<&(String, Handle<AnimationClip>)> {
+        self.clips.get(self.current)
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index < self.clips.len() {
+            self.current = index;
+            self.paused = false;
+        }
+    }
+
+    pub fn next(&mut self) {
+        if !self.clips.is_empty() {
+            self.select((self.current + 1) % self.clips.len());
+        }
+    }
+}
+
+fn collect_animations(
+    scene_handle: Option<Res<SceneHandle>>,
+    gltf_assets: Res<Assets<Gltf>>,
+    mut animations: ResMut<SceneAnimations>,
+) {
+    let Some(scene_handle) = scene_handle.filter(|scene_handle| scene_handle.is_loaded) else {
+        if animations.gltf.is_some() {
+            *animations = SceneAnimations::default();
+        }
+        return;
+    };
+    let id = scene_handle.gltf_handle.id();
+    if animations.gltf == Some(id) {
+        return;
+    }
+    let Some(gltf) = gltf_assets.get(&scene_handle.gltf_handle) else {
+        return;
+    };
+
+    let clips = gltf
+        .animations
+        .iter()
+        .enumerate()
+        .map(|(i, clip)| {
+            let name = gltf
+                .named_animations
+                .iter()
+                .find(|(_, named)| *named == clip)
+                .map(|(name, _)| name.clone())
+                .unwrap_or_else(|| format!("Animation {i}"));
+            (name, clip.clone())
+        })
+        .collect::<Vec<_>>();
+    if !clips.is_empty() {
+        info!("{} animations: {:?}", clips.len(), clips.iter().map(|(name, _)| name).collect::<Vec<_>>());
+    }
+    *animations = SceneAnimations {
+        clips,
+        gltf: Some(id),
+        ..default()
+    };
+}
+
+fn animation_controls(keyboard_input: Res<Input<KeyCode>>, mut animations: ResMut<SceneAnimations>) {
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        animations.paused = !animations.paused;
+    }
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        animations.next();
+    }
+}
+
+/// Starts the current clip on new players and when the clip changes, and applies the settings.
+/// Only players in the `SceneHandle` instance are driven: scenes from other files have other node paths.
+fn sync_animation_players(
+    mut animations: ResMut<SceneAnimations>,
+    scene_handle: Option<Res<SceneHandle>>,
+    scene_spawner: Res<SceneSpawner>,
+    mut players: Query<&mut AnimationPlayer>,
+    mut playing: Local<Option<Handle<AnimationClip>>>,
+) {
+    let (Some((_, clip)), Some(instance_id)) = (
+        animations.current_clip().cloned(),
+        scene_handle.and_then(|scene_handle| scene_handle.instance_id()),
+    ) else {
+        *playing = None;
+        return;
+    };
+    let scene_players: Vec<Entity> = scene_spawner
+        .iter_instance_entities(instance_id)
+        .filter(|entity| players.contains(*entity))
+        .collect();
+    let clip_changed = playing.as_ref() != Some(&clip);
+    let any_added = scene_players
+        .iter()
+        .any(|entity| players.get_mut(*entity).is_ok_and(|player| player.is_added()));
+    if !clip_changed && !animations.is_changed() && !any_added {
+        return;
+    }
+    *playing = Some(clip.clone());
+
+    let seek = animations.seek.take();
+    for entity in scene_players {
+        let Ok(mut player) = players.get_mut(entity) else {
+            continue;
+        };
+        if clip_changed || player.is_added() {
+            player.play(clip.clone());
+        }
+        if animations.looping {
+            player.repeat();
+        } else {
+            player.stop_repeating();
+        }
+        player.set_speed(animations.speed);
+        if let Some(seek) = seek {
+            player.set_elapsed(seek);
+        }
+        if animations.paused {
+            player.pause();
+        } else {
+            player.resume();
+        }
+    }
+}
+
+/// L and U are also attack inputs, so the light keys only work while the free camera is enabled.
 fn update_lights(
     key_input: Res<Input<KeyCode>>,
     time: Res<Time>,
+    controllers: Query<&CameraController>,
     mut query: Query<(&mut Transform, &mut DirectionalLight)>,
     mut animate_directional_light: Local<bool>,
 ) {
+    let viewing = controllers.iter().any(|controller| controller.enabled);
     for (_, mut light) in &mut query {
-        if key_input.just_pressed(KeyCode::U) {
+        if viewing && key_input.just_pressed(KeyCode::U) {
             light.shadows_enabled = !light.shadows_enabled;
         }
     }
 
-    if key_input.just_pressed(KeyCode::L) {
+    if viewing && key_input.just_pressed(KeyCode::L) {
         *animate_directional_light = !*animate_directional_light;
     }
     if *animate_directional_light {
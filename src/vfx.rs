@@ -0,0 +1,316 @@
+//! 战斗特效: 命中/防御时在攻击框和受击框的重叠处播放动作数据中配置的特效,
+//! 同时震屏、短暂的顿帧(hitstop)和闪白; KO 时震动和顿帧更强.
+
+use bevy::prelude::*;
+use bevy_sprite3d::{AtlasSprite3d, AtlasSprite3dComponent, Sprite3dParams};
+use rand::Rng;
+use crate::{CharacterName, CombatEvent, Effect, FlatCamera, GameState, GameViewport, MainCamera, UID};
+use crate::billboard::{CombatPlane, FightView};
+use crate::loading::{Characters, EffectsTextureAtlas};
+
+pub struct VfxPlugin;
+
+impl Plugin for VfxPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameViewport>()
+            .init_resource::<ScreenShake>()
+            .init_resource::<Hitstop>()
+            .add_systems(OnEnter(GameState::Playing), setup)
+            .add_systems(PreUpdate, unshake_cameras)
+            .add_systems(Update, (spawn_effects, animate_effects, tick_hitstop, sync_flash, flash).run_if(in_state(GameState::Playing)))
+            .add_systems(
+                PostUpdate,
+                shake_cameras
+                    .before(bevy::transform::TransformSystem::TransformPropagate)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), cleanup);
+    }
+}
+
+/// 普通命中的顿帧时长(秒)
+const HITSTOP_SECONDS: f32 = 0.06;
+const BLOCK_HITSTOP_SECONDS: f32 = 0.04;
+const KO_HITSTOP_SECONDS: f32 = 0.4;
+/// 每次命中增加的震动强度, 强度在 0..1 之间, 每秒衰减 `SHAKE_DECAY`
+const HIT_TRAUMA: f32 = 0.3;
+const BLOCK_TRAUMA: f32 = 0.15;
+const KO_TRAUMA: f32 = 0.9;
+const SHAKE_DECAY: f32 = 1.5;
+/// 强度为 1 时 3D 相机的最大偏移(米), 2D 相机按战斗平面的比例换算成像素
+const MAX_SHAKE: f32 = 0.3;
+
+/// 震屏: 强度随时间衰减, 实际偏移为强度的平方乘以随机方向
+#[derive(Resource, Default)]
+pub struct ScreenShake {
+    pub trauma: f32,
+    /// 本帧加到相机上的偏移, 下一帧开始时撤销, 不影响其他系统设置的相机位置
+    offset: Vec3,
+}
+
+impl ScreenShake {
+    pub fn add(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).min(1.);
+    }
+}
+
+/// 顿帧: 期间所有角色停止动画和物理模拟
+#[derive(Resource, Default)]
+pub struct Hitstop(Timer);
+
+impl Hitstop {
+    pub fn start(&mut self, seconds: f32) {
+        // 顿帧不叠加, 取剩余时间更长的一次
+        if !self.active() || self.0.remaining_secs() < seconds {
+            self.0 = Timer::from_seconds(seconds, TimerMode::Once);
+        }
+    }
+
+    pub fn active(&self) -> bool {
+        !self.0.finished() && self.0.duration().as_secs_f32() > 0.
+    }
+}
+
+/// 正在播放的特效, 播完最后一帧后销毁
+#[derive(Component)]
+struct VfxEffect {
+    timer: Timer,
+    frames: usize,
+    index: usize,
+}
+
+/// 顿帧时的闪白, 与 HUD 一样按 `GameViewport` 定位, 不盖住 Inspector
+#[derive(Component)]
+struct HitFlash;
+
+fn setup(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.),
+                height: Val::Percent(100.),
+                ..default()
+            },
+            background_color: Color::NONE.into(),
+            z_index: ZIndex::Global(10),
+            ..default()
+        },
+        HitFlash,
+    ));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_effects(
+    mut commands: Commands,
+    mut events: EventReader<CombatEvent>,
+    mut shake: ResMut<ScreenShake>,
+    mut hitstop: ResMut<Hitstop>,
+    view: Res<FightView>,
+    plane: Res<CombatPlane>,
+    characters: Res<Characters>,
+    effects_texture_atlas: Res<EffectsTextureAtlas>,
+    mut sprite_params: Sprite3dParams,
+    fighters: Query<(&UID, &CharacterName)>,
+) {
+    for event in events.iter() {
+        let (attacker, attack_action, position, blocked) = match event {
+            CombatEvent::Damage { attacker, attack_action, position, .. } => {
+                shake.add(HIT_TRAUMA);
+                hitstop.start(HITSTOP_SECONDS);
+                (attacker, attack_action, position, false)
+            }
+            CombatEvent::Block { attacker, attack_action, position, .. } => {
+                shake.add(BLOCK_TRAUMA);
+                hitstop.start(BLOCK_HITSTOP_SECONDS);
+                (attacker, attack_action, position, true)
+            }
+            CombatEvent::KO(_) => {
+                shake.add(KO_TRAUMA);
+                hitstop.start(KO_HITSTOP_SECONDS);
+                continue;
+            }
+            CombatEvent::RoundOver { .. } => continue,
+        };
+
+        let action = fighters
+            .iter()
+            .find(|(uid, _)| *uid == attacker)
+            .and_then(|(_, character_name)| characters.get(character_name.as_str()))
+            .and_then(|character| character.actions.get(attack_action));
+        let effect = action.and_then(|action| if blocked { action.block_effect.as_ref() } else { action.hit_effect.as_ref() });
+        let Some(effect) = effect else {
+            continue;
+        };
+        let Some(texture_atlas) = effects_texture_atlas.get(effect.sprite.path()) else {
+            continue;
+        };
+        spawn_effect(&mut commands, &mut sprite_params, *view, &plane, effect, texture_atlas, *position);
+    }
+}
+
+/// 2D 模式生成精灵, 2.5D 模式在战斗平面上生成公告板
+fn spawn_effect(
+    commands: &mut Commands,
+    sprite_params: &mut Sprite3dParams,
+    view: FightView,
+    plane: &CombatPlane,
+    effect: &Effect,
+    texture_atlas: &Handle<TextureAtlas>,
+    position: Vec2,
+) {
+    let frames = sprite_params.atlases.get(texture_atlas).map_or(0, |texture_atlas| texture_atlas.len());
+    if frames == 0 {
+        return;
+    }
+    let scale = effect.scale.unwrap_or(1.);
+    let vfx = VfxEffect {
+        timer: Timer::from_seconds(effect.duration / frames as f32, TimerMode::Repeating),
+        frames,
+        index: 0,
+    };
+
+    match view {
+        FightView::Flat => {
+            commands.spawn((
+                SpriteSheetBundle {
+                    texture_atlas: texture_atlas.clone(),
+                    sprite: TextureAtlasSprite::new(0),
+                    transform: Transform::from_translation(position.extend(5.)).with_scale(Vec3::splat(scale)),
+                    ..default()
+                },
+                vfx,
+                Name::new("Vfx"),
+            ));
+        }
+        FightView::Billboard => {
+            // 略微靠近相机, 不被角色公告板挡住
+            let translation = plane.to_world(position) + Vec3::Z * 0.05;
+            commands.spawn((
+                AtlasSprite3d {
+                    atlas: texture_atlas.clone(),
+                    pixels_per_metre: plane.pixels_per_metre / scale,
+                    alpha_mode: AlphaMode::Blend,
+                    unlit: true,
+                    double_sided: true,
+                    index: 0,
+                    transform: Transform::from_translation(translation),
+                    ..default()
+                }.bundle(sprite_params),
+                vfx,
+                Name::new("Vfx"),
+            ));
+        }
+    }
+}
+
+fn animate_effects(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut effects: Query<(Entity, &mut VfxEffect, Option<&mut TextureAtlasSprite>, Option<&mut AtlasSprite3dComponent>)>,
+) {
+    for (entity, mut effect, sprite, sprite3d) in effects.iter_mut() {
+        effect.timer.tick(time.delta());
+        if !effect.timer.just_finished() {
+            continue;
+        }
+        effect.index += 1;
+        if effect.index >= effect.frames {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        if let Some(mut sprite) = sprite {
+            sprite.index = effect.index;
+        }
+        if let Some(mut sprite3d) = sprite3d {
+            sprite3d.index = effect.index;
+        }
+    }
+}
+
+fn tick_hitstop(time: Res<Time>, mut hitstop: ResMut<Hitstop>) {
+    if hitstop.active() {
+        hitstop.0.tick(time.delta());
+    }
+}
+
+fn sync_flash(viewport: Res<GameViewport>, mut flashes: Query<(&mut Style, Ref<HitFlash>)>) {
+    let (width, height) = match **viewport {
+        Some(rect) => (Val::Px(rect.width()), Val::Px(rect.height())),
+        None => (Val::Percent(100.), Val::Percent(100.)),
+    };
+    for (mut style, flash) in flashes.iter_mut() {
+        if !viewport.is_changed() && !flash.is_added() {
+            continue;
+        }
+        style.width = width;
+        style.height = height;
+    }
+}
+
+fn flash(hitstop: Res<Hitstop>, mut flashes: Query<&mut BackgroundColor, With<HitFlash>>) {
+    if !hitstop.is_changed() {
+        return;
+    }
+    let color = if hitstop.active() {
+        Color::rgba(1., 1., 1., 0.25 * hitstop.0.percent_left())
+    } else {
+        Color::NONE
+    };
+    for mut background in flashes.iter_mut() {
+        background.0 = color;
+    }
+}
+
+fn unshake_cameras(
+    mut shake: ResMut<ScreenShake>,
+    plane: Res<CombatPlane>,
+    mut main_cameras: Query<&mut Transform, (With<MainCamera>, Without<FlatCamera>)>,
+    mut flat_cameras: Query<&mut Transform, (With<FlatCamera>, Without<MainCamera>)>,
+) {
+    if shake.offset == Vec3::ZERO {
+        return;
+    }
+    for mut transform in main_cameras.iter_mut() {
+        transform.translation -= shake.offset;
+    }
+    for mut transform in flat_cameras.iter_mut() {
+        transform.translation -= shake.offset * plane.pixels_per_metre;
+    }
+    shake.offset = Vec3::ZERO;
+}
+
+fn shake_cameras(
+    time: Res<Time>,
+    mut shake: ResMut<ScreenShake>,
+    plane: Res<CombatPlane>,
+    mut main_cameras: Query<&mut Transform, (With<MainCamera>, Without<FlatCamera>)>,
+    mut flat_cameras: Query<&mut Transform, (With<FlatCamera>, Without<MainCamera>)>,
+) {
+    if shake.trauma <= 0. {
+        return;
+    }
+    let mut rng = rand::thread_rng();
+    let strength = shake.trauma * shake.trauma * MAX_SHAKE;
+    shake.offset = Vec3::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0), 0.) * strength;
+    shake.trauma = (shake.trauma - SHAKE_DECAY * time.delta_seconds()).max(0.);
+
+    for mut transform in main_cameras.iter_mut() {
+        transform.translation += shake.offset;
+    }
+    for mut transform in flat_cameras.iter_mut() {
+        transform.translation += shake.offset * plane.pixels_per_metre;
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn cleanup(
+    mut commands: Commands,
+    mut hitstop: ResMut<Hitstop>,
+    query: Query<Entity, Or<(With<VfxEffect>, With<HitFlash>)>>,
+) {
+    *hitstop = Hitstop::default();
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
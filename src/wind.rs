@@ -0,0 +1,107 @@
+//! 风: 草和场景植物的摇摆由 `Wind` 控制, 风浪沿风向推进, 不同位置的相位不同, 再叠加随风移动的阵风噪声.
+//! 参数写进每个 `CustomMaterial` 的 uniform, 在 Inspector 的 Resources 面板中调整.
+
+use bevy::prelude::*;
+use crate::CustomMaterial;
+
+pub struct WindPlugin;
+
+impl Plugin for WindPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Wind>()
+            .register_type::<Wind>()
+            .add_systems(Update, apply_wind);
+    }
+}
+
+#[derive(Resource, Reflect, Clone)]
+#[reflect(Resource)]
+pub struct Wind {
+    /// 风向, 世界坐标的 xz 平面
+    pub direction: Vec2,
+    /// 叶尖的最大摆动(米)
+    pub strength: f32,
+    /// 风浪推进的速度(米/秒)
+    pub speed: f32,
+    /// 相邻两道风浪的间距(米)
+    pub wavelength: f32,
+    /// 阵风叠加的最大摆动(米)
+    pub gust_strength: f32,
+    /// 阵风的大小(米), 越大一阵风覆盖的范围越大
+    pub gust_scale: f32,
+}
+
+impl Default for Wind {
+    fn default() -> Self {
+        Self {
+            direction: Vec2::X,
+            strength: 0.08,
+            speed: 2.,
+            wavelength: 4.,
+            gust_strength: 0.12,
+            gust_scale: 6.,
+        }
+    }
+}
+
+pub use uniform::WindUniform;
+
+// `ShaderType` 派生宏为每个字段生成的检查函数不会被调用, 放在单独的模块里关掉 dead_code
+#[allow(dead_code)]
+mod uniform {
+    use bevy::prelude::*;
+    use bevy::render::render_resource::ShaderType;
+
+    /// 着色器中的 `Wind`, 见 `shaders/wind.wgsl`
+    #[derive(ShaderType, Clone, Copy, Debug, Default)]
+    pub struct WindUniform {
+        pub direction: Vec2,
+        pub strength: f32,
+        pub speed: f32,
+        pub wavelength: f32,
+        pub gust_strength: f32,
+        pub gust_scale: f32,
+    }
+}
+
+impl From<&Wind> for WindUniform {
+    fn from(wind: &Wind) -> Self {
+        Self {
+            direction: wind.direction.normalize_or_zero(),
+            strength: wind.strength,
+            speed: wind.speed,
+            wavelength: wind.wavelength.max(0.01),
+            gust_strength: wind.gust_strength,
+            gust_scale: wind.gust_scale.max(0.01),
+        }
+    }
+}
+
+
+/// 风改变时更新所有材质, 新建的材质补上当前的风
+fn apply_wind(
+    wind: Res<Wind>,
+    mut events: EventReader<AssetEvent<CustomMaterial>>,
+    mut materials: ResMut<Assets<CustomMaterial>>,
+) {
+    let uniform = WindUniform::from(wind.as_ref());
+    if wind.is_changed() {
+        events.clear();
+        for (_, material) in materials.iter_mut() {
+            material.wind = uniform;
+        }
+        return;
+    }
+    let created: Vec<Handle<CustomMaterial>> = events
+        .iter()
+        .filter_map(|event| match event {
+            AssetEvent::Created { handle } => Some(handle.clone_weak()),
+            _ => None,
+        })
+        .collect();
+    for handle in created {
+        if let Some(material) = materials.get_mut(&handle) {
+            material.wind = uniform;
+        }
+    }
+}